@@ -0,0 +1,39 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use proto_rust::lexer::Lexer;
+
+// A few lines of typical Proto, including non-ASCII text, repeated until the
+// source is at least `size` bytes long.
+fn source(size: usize) -> String {
+    let chunk = "\
+fun fib(n) {
+    // naïve recursion
+    if (n < 2) return n;
+    return fib(n - 1) + fib(n - 2);
+}
+var greeting = \"héllo, wörld ✓\";
+print greeting + \" \" + fib(10.5 * 2);
+";
+    let mut code = String::with_capacity(size + chunk.len());
+    while code.len() < size {
+        code.push_str(chunk);
+    }
+    return code;
+}
+
+// Throughput should stay flat as the input grows if lexing is linear.
+fn lex(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lexer");
+    group.sample_size(10);
+    for megabytes in [1, 2, 4, 8] {
+        let code = source(megabytes * 1024 * 1024);
+        group.throughput(Throughput::Bytes(code.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(format!("{}MB", megabytes)), &code, |b, code| {
+            b.iter(|| Lexer::new(code.clone(), 0).tokens())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lex);
+criterion_main!(benches);
@@ -0,0 +1,67 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use proto_rust::lexer::Lexer;
+use proto_rust::parser::Parser;
+
+// `statements` generated statements cycling through declarations, control
+// flow and expressions.
+fn source(statements: usize) -> String {
+    let mut code = String::new();
+    for i in 0..statements {
+        match i % 4 {
+            0 => code.push_str(&format!("var v{} = {} + 2 * (3 - {});\n", i, i, i)),
+            1 => code.push_str(&format!("if (v{} > 10) print v{}; else print -v{};\n", i - 1, i - 1, i - 1)),
+            2 => code.push_str(&format!("fun f{}(a, b) {{ return a * b + {}; }}\n", i, i)),
+            _ => code.push_str(&format!("print f{}(v{}, \"s\") == nil or true;\n", i - 1, i - 3)),
+        }
+    }
+    return code;
+}
+
+// Throughput should stay flat as the input grows if parsing is linear.
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parser");
+    group.sample_size(10);
+    for statements in [25_000, 50_000, 100_000] {
+        let tokens = Lexer::new(source(statements), 0).tokens();
+        group.throughput(Throughput::Elements(statements as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(statements), &tokens, |b, tokens| {
+            b.iter(|| Parser::new(tokens.clone()).parse().unwrap())
+        });
+    }
+    group.finish();
+}
+
+// The two ways of walking the token stream the parser has used: taking each
+// token with `Vec::remove(0)`, which shifts the rest down every time, and
+// moving an index along it. The first is quadratic, so the sizes are small.
+fn cursor(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cursor");
+    group.sample_size(10);
+    for statements in [250, 500, 1_000] {
+        let tokens = Lexer::new(source(statements), 0).tokens();
+        group.throughput(Throughput::Elements(statements as u64));
+        group.bench_with_input(BenchmarkId::new("remove", statements), &tokens, |b, tokens| {
+            b.iter(|| {
+                let mut tokens = tokens.clone();
+                while tokens.len() > 1 {
+                    black_box(tokens.remove(0));
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("index", statements), &tokens, |b, tokens| {
+            b.iter(|| {
+                let tokens = tokens.clone();
+                let mut current = 0;
+                while current < tokens.len() - 1 {
+                    black_box(tokens[current].clone());
+                    current += 1;
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse, cursor);
+criterion_main!(benches);
@@ -0,0 +1,82 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::function::{Callable, Function};
+use crate::diagnostic::Code;
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::token::Token;
+use crate::value::Value;
+
+pub struct Class {
+    name: Token,
+    superclass: Option<Rc<Class>>,
+    methods: HashMap<String, Rc<Function>>,
+}
+
+impl Class {
+    pub fn new(name: Token, superclass: Option<Rc<Class>>, methods: HashMap<String, Rc<Function>>) -> Class {
+        Class {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    pub fn get_name(&self) -> &Token { &self.name }
+
+    // Looks `name` up in this class and then along its superclass chain.
+    pub fn find_method(&self, name: &str) -> Option<Rc<Function>> {
+        if let Some(method) = self.methods.get(name) {
+            return Some(Rc::clone(method));
+        }
+        return self.superclass.as_ref().and_then(|superclass| superclass.find_method(name));
+    }
+}
+
+// Calling a class constructs a new instance and runs its `init` method, if any.
+impl Callable for Rc<Class> {
+    fn arity(&self) -> usize {
+        return self.find_method("init").map_or(0, |init| init.arity());
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let instance = Rc::new(RefCell::new(Instance::new(Rc::clone(self))));
+        if let Some(init) = self.find_method("init") {
+            init.bind(Rc::clone(&instance)).call(interpreter, arguments)?;
+        }
+        return Ok(Value::Instance(instance));
+    }
+}
+
+pub struct Instance {
+    class: Rc<Class>,
+    fields: HashMap<String, Value>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Instance {
+        Instance {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    pub fn get_class(&self) -> &Rc<Class> { &self.class }
+
+    // Fields shadow methods; methods are returned bound to `instance`.
+    pub fn get(instance: &Rc<RefCell<Instance>>, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = instance.borrow().fields.get(&name.literal) {
+            return Ok(value.clone());
+        }
+        let method = instance.borrow().class.find_method(&name.literal);
+        match method {
+            Some(method) => return Ok(Value::Function(Rc::new(method.bind(Rc::clone(instance))))),
+            None => return Err(RuntimeError::new(Code::UndefinedProperty, name.clone(), format!("Undefined property '{}'", name.literal)))
+        }
+    }
+
+    pub fn set(&mut self, name: &Token, value: Value) {
+        self.fields.insert(name.literal.clone(), value);
+    }
+}
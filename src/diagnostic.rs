@@ -0,0 +1,398 @@
+use std::env;
+use std::fmt::Write;
+use std::io::{self, IsTerminal};
+
+use crate::token::Span;
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+// Stable identifier for each kind of error, so that tools can tell errors
+// apart without matching on their messages. Codes are grouped by the stage
+// that reports them and are never reused.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Code {
+    UnexpectedCharacter,
+    UnterminatedString,
+    InvalidEscape,
+    MalformedNumber,
+    UnterminatedComment,
+
+    ExpectedToken,
+    ExpectedExpression,
+    InvalidAssignmentTarget,
+    NestingTooDeep,
+
+    Redeclaration,
+    ReadInOwnInitializer,
+    ReturnOutsideFunction,
+    ReturnFromInitializer,
+    InheritFromSelf,
+    ThisOutsideClass,
+    SuperOutsideClass,
+    SuperWithoutSuperclass,
+
+    // Broken invariants that earlier stages should have ruled out.
+    Internal,
+    UndefinedVariable,
+    UndefinedProperty,
+    OperandType,
+    NotCallable,
+    ArityMismatch,
+    StackOverflow,
+    InvalidSuperclass,
+    NotAnInstance,
+}
+
+impl Code {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Code::UnexpectedCharacter => "E0001",
+            Code::UnterminatedString => "E0002",
+            Code::InvalidEscape => "E0003",
+            Code::MalformedNumber => "E0004",
+            Code::UnterminatedComment => "E0005",
+
+            Code::ExpectedToken => "E0101",
+            Code::ExpectedExpression => "E0102",
+            Code::InvalidAssignmentTarget => "E0103",
+            Code::NestingTooDeep => "E0104",
+
+            Code::Redeclaration => "E0201",
+            Code::ReadInOwnInitializer => "E0202",
+            Code::ReturnOutsideFunction => "E0203",
+            Code::ReturnFromInitializer => "E0204",
+            Code::InheritFromSelf => "E0205",
+            Code::ThisOutsideClass => "E0206",
+            Code::SuperOutsideClass => "E0207",
+            Code::SuperWithoutSuperclass => "E0208",
+
+            Code::Internal => "E0300",
+            Code::UndefinedVariable => "E0301",
+            Code::UndefinedProperty => "E0302",
+            Code::OperandType => "E0303",
+            Code::NotCallable => "E0304",
+            Code::ArityMismatch => "E0305",
+            Code::StackOverflow => "E0306",
+            Code::InvalidSuperclass => "E0307",
+            Code::NotAnInstance => "E0308",
+        }
+    }
+}
+
+// A secondary span pointed at by a diagnostic, with a short explanation.
+#[derive(Debug,Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(span: Span, message: String) -> Label {
+        Label {
+            span,
+            message,
+        }
+    }
+}
+
+// A problem found in a program, in a form that can be rendered against its
+// source. `span` is the primary location, `labels` point at related code.
+#[derive(Debug,Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Code,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: Code, span: Span, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message,
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn with_labels(mut self, labels: &[Label]) -> Diagnostic {
+        self.labels.extend_from_slice(labels);
+        return self;
+    }
+
+    pub fn with_notes(mut self, notes: &[String]) -> Diagnostic {
+        self.notes.extend_from_slice(notes);
+        return self;
+    }
+
+    pub fn with_help(mut self, help: Option<String>) -> Diagnostic {
+        self.help = help;
+        return self;
+    }
+}
+
+pub struct SourceFile {
+    pub name: String,
+    pub source: String,
+    // Byte offset at which each line starts.
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    // Text of a 1-based line, without its line ending. Lines past the end of
+    // the file are empty.
+    pub fn line(&self, line: usize) -> &str {
+        let start = match self.line_starts.get(line.wrapping_sub(1)) {
+            Some(start) => *start,
+            None => return "",
+        };
+        let end = self.line_starts.get(line).map_or(self.source.len(), |end| *end);
+        return self.source[start..end].trim_end_matches(['\n', '\r']);
+    }
+
+    // 1-based line and column of a byte offset, counting columns in chars.
+    // Offsets past the end are clamped to it.
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        let start = self.line_starts[line - 1];
+        let column = self.source[start..offset].chars().count() + 1;
+        return (line, column);
+    }
+}
+
+// Every piece of source text seen so far. A file id, as stored in a Span, is
+// an index into this map.
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap {
+            files: Vec::new(),
+        }
+    }
+
+    // Adds a file and returns its id.
+    pub fn add(&mut self, name: &str, source: String) -> usize {
+        let mut line_starts = vec![0];
+        line_starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+        self.files.push(SourceFile {
+            name: name.to_owned(),
+            source,
+            line_starts,
+        });
+        return self.files.len() - 1;
+    }
+
+    pub fn get(&self, file: usize) -> &SourceFile {
+        &self.files[file]
+    }
+}
+
+// Whether diagnostics written to stderr should be colored.
+pub fn stderr_color() -> bool {
+    return io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+
+// Renders diagnostics in the style of modern compilers:
+//
+//   error[E0101]: Expected ';' after value, found '1'
+//    --> script.proto:2:12
+//     |
+//   2 | print "ok" 1;
+//     |            ^
+//
+pub struct Emitter<'a> {
+    sources: &'a SourceMap,
+    color: bool,
+}
+
+impl<'a> Emitter<'a> {
+    pub fn new(sources: &'a SourceMap, color: bool) -> Emitter<'a> {
+        Emitter {
+            sources,
+            color,
+        }
+    }
+
+    pub fn emit(&self, diagnostic: &Diagnostic) {
+        eprint!("{}", self.render(diagnostic));
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let file = self.sources.get(diagnostic.span.file);
+        let severity_color = match diagnostic.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+
+        // Every span to underline, the primary one first. Labels in other
+        // files are left out.
+        let mut marks: Vec<(Span, char, &str)> = vec![(diagnostic.span, '^', "")];
+        for label in &diagnostic.labels {
+            if label.span.file == diagnostic.span.file {
+                marks.push((label.span, '-', label.message.as_str()));
+            }
+        }
+        let mut lines: Vec<usize> = marks.iter().map(|(span, _, _)| span.line).collect();
+        lines.sort();
+        lines.dedup();
+        let gutter = lines.last().map_or(1, |line| line.to_string().len());
+        let pad = " ".repeat(gutter);
+
+        let mut out = String::new();
+        let heading = format!("{}[{}]", diagnostic.severity.name(), diagnostic.code.as_str());
+        let _ = writeln!(out, "{}: {}", self.paint(severity_color, &heading), self.paint(BOLD, &diagnostic.message));
+        let _ = writeln!(out, "{}{} {}:{}:{}", pad, self.paint(BLUE, "-->"), file.name, diagnostic.span.line, diagnostic.span.column);
+        let _ = writeln!(out, "{} {}", pad, self.paint(BLUE, "|"));
+
+        let mut previous: Option<usize> = None;
+        for line in lines {
+            if previous.is_some_and(|previous| line > previous + 1) {
+                let _ = writeln!(out, "{}", self.paint(BLUE, "..."));
+            }
+            previous = Some(line);
+
+            let text = file.line(line);
+            let number = format!("{:>width$}", line, width = gutter);
+            let _ = writeln!(out, "{} {} {}", self.paint(BLUE, &number), self.paint(BLUE, "|"), text);
+            for (span, marker, message) in marks.iter().filter(|(span, _, _)| span.line == line) {
+                let (indent, width) = Emitter::underline(text, &file.source, span);
+                let color = if *marker == '^' { severity_color } else { BLUE };
+                let underline = marker.to_string().repeat(width);
+                let label = if message.is_empty() { String::new() } else { format!(" {}", message) };
+                let _ = writeln!(out, "{} {} {}{}", pad, self.paint(BLUE, "|"), indent, self.paint(color, &(underline + &label)));
+            }
+        }
+
+        if !diagnostic.notes.is_empty() || diagnostic.help.is_some() {
+            let _ = writeln!(out, "{} {}", pad, self.paint(BLUE, "|"));
+        }
+        for note in &diagnostic.notes {
+            let _ = writeln!(out, "{} {} note: {}", pad, self.paint(BLUE, "="), note);
+        }
+        if let Some(help) = &diagnostic.help {
+            let _ = writeln!(out, "{} {} {}: {}", pad, self.paint(BLUE, "="), self.paint(CYAN, "help"), help);
+        }
+        out.push('\n');
+        return out;
+    }
+
+    // Whitespace leading up to `span` on its line, keeping tabs so the
+    // underline lines up, and the underline's width in chars. Spans running
+    // past the line are cut off at its end; empty spans get one char.
+    fn underline(text: &str, source: &str, span: &Span) -> (String, usize) {
+        let indent: String = text.chars()
+            .take(span.column.saturating_sub(1))
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+        let rest: String = text.chars().skip(span.column.saturating_sub(1)).collect();
+        let end = span.end.min(source.len()).max(span.start);
+        let spanned = source[span.start..end].chars().count();
+        let width = spanned.min(rest.chars().count()).max(1);
+        return (indent, width);
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            return format!("{}{}{}", color, text, RESET);
+        }
+        return text.to_owned();
+    }
+}
+
+// Renders diagnostics as JSON, one object per line, for editors and other
+// tools. Positions are 1-based and `end` points just past the span:
+//
+//   {"severity":"error","code":"E0101","message":"...","file":"a.proto",
+//    "start":{"line":2,"column":12},"end":{"line":2,"column":13},
+//    "labels":[],"notes":[],"help":null}
+//
+pub struct JsonEmitter<'a> {
+    sources: &'a SourceMap,
+}
+
+impl<'a> JsonEmitter<'a> {
+    pub fn new(sources: &'a SourceMap) -> JsonEmitter<'a> {
+        JsonEmitter {
+            sources,
+        }
+    }
+
+    pub fn emit(&self, diagnostic: &Diagnostic) {
+        eprintln!("{}", self.render(diagnostic));
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let labels: Vec<String> = diagnostic.labels.iter()
+            .map(|label| format!("{{\"message\":{},{}}}", json_string(&label.message), self.location(&label.span)))
+            .collect();
+        let notes: Vec<String> = diagnostic.notes.iter().map(|note| json_string(note)).collect();
+        let help = diagnostic.help.as_ref().map_or("null".to_owned(), |help| json_string(help));
+        return format!("{{\"severity\":{},\"code\":{},\"message\":{},{},\"labels\":[{}],\"notes\":[{}],\"help\":{}}}",
+            json_string(diagnostic.severity.name()),
+            json_string(diagnostic.code.as_str()),
+            json_string(&diagnostic.message),
+            self.location(&diagnostic.span),
+            labels.join(","),
+            notes.join(","),
+            help);
+    }
+
+    // The "file", "start" and "end" members for a span.
+    fn location(&self, span: &Span) -> String {
+        let file = self.sources.get(span.file);
+        let (end_line, end_column) = file.position(span.end.max(span.start));
+        return format!("\"file\":{},\"start\":{{\"line\":{},\"column\":{}}},\"end\":{{\"line\":{},\"column\":{}}}",
+            json_string(&file.name), span.line, span.column, end_line, end_column);
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", ch as u32);
+            }
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    return out;
+}
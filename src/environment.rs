@@ -0,0 +1,87 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::interpreter::RuntimeError;
+use crate::diagnostic::Code;
+use crate::token::Token;
+use crate::value::Value;
+
+pub struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment {
+            values: HashMap::new(),
+            enclosing: None,
+        }
+    }
+
+    // A nested scope; names not found here are looked up in `enclosing`.
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Environment {
+        Environment {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    // The scope `distance` levels out from `environment`, as computed by the
+    // resolver.
+    pub fn ancestor(environment: &Rc<RefCell<Environment>>, distance: usize) -> Rc<RefCell<Environment>> {
+        let mut environment = Rc::clone(environment);
+        for _ in 0..distance {
+            let enclosing = match &environment.borrow().enclosing {
+                Some(enclosing) => Rc::clone(enclosing),
+                None => break,
+            };
+            environment = enclosing;
+        }
+        return environment;
+    }
+
+    // Declaring a name that already exists silently replaces its value.
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_owned(), value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        match self.lookup(&name.literal) {
+            Some(value) => return Ok(value),
+            None => return Err(RuntimeError::new(Code::UndefinedVariable, name.clone(), format!("Undefined variable '{}'", name.literal)))
+        }
+    }
+
+    // Names defined directly in this scope, in sorted order.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.values.keys().cloned().collect();
+        names.sort();
+        return names;
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        if let Some(value) = self.values.get(name) {
+            return Some(value.clone());
+        }
+        return self.enclosing.as_ref().and_then(|enclosing| enclosing.borrow().lookup(name));
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        if let Some(slot) = self.values.get_mut(&name.literal) {
+            *slot = value;
+            return Ok(());
+        }
+        match &self.enclosing {
+            Some(enclosing) => return enclosing.borrow_mut().assign(name, value),
+            None => return Err(RuntimeError::new(Code::UndefinedVariable, name.clone(), format!("Undefined variable '{}'", name.literal)))
+        }
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
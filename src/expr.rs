@@ -1,15 +1,34 @@
-use crate::token::Token;
+use std::any::Any;
+use std::fmt::Debug;
+use std::rc::Rc;
+use crate::token::{Token, Span};
+use crate::interpreter::{RuntimeError, Unwind};
+use crate::value::Value;
 
-pub trait ExprVisitor {
-    fn visit_literal(&mut self, literal: &LiteralExpr) -> f64;
-    fn visit_unary(&mut self, unary: &UnaryExpr) -> f64;
-    fn visit_binary(&mut self, binary: &BinaryExpr) -> f64;
+pub trait ExprVisitor<R> {
+    fn visit_literal(&mut self, expr: &LiteralExpr) -> R;
+    fn visit_unary(&mut self, expr: &UnaryExpr) -> R;
+    fn visit_binary(&mut self, expr: &BinaryExpr) -> R;
+    fn visit_variable(&mut self, expr: &VariableExpr) -> R;
+    fn visit_assign(&mut self, expr: &AssignExpr) -> R;
+    fn visit_logical(&mut self, expr: &LogicalExpr) -> R;
+    fn visit_call(&mut self, expr: &CallExpr) -> R;
+    fn visit_get(&mut self, expr: &GetExpr) -> R;
+    fn visit_set(&mut self, expr: &SetExpr) -> R;
+    fn visit_this(&mut self, expr: &ThisExpr) -> R;
+    fn visit_super(&mut self, expr: &SuperExpr) -> R;
 }
 
-pub trait Expr {
-    fn accept(&self, visitor: &mut dyn ExprVisitor) -> f64;
+pub trait ExprAccept<R> {
+    fn accept(&self, visitor: &mut dyn ExprVisitor<R>) -> R;
 }
 
+pub trait Expr: ExprAccept<Result<Value, RuntimeError>> + ExprAccept<()> + ExprAccept<String> + Debug {
+    fn span(&self) -> Span;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+#[derive(Debug)]
 pub struct LiteralExpr {
     token: Token,
 }
@@ -21,12 +40,22 @@ impl LiteralExpr {
     pub fn get_token(&self) -> &Token { &self.token }
 }
 
-impl Expr for LiteralExpr {
-    fn accept(&self, visitor: &mut dyn ExprVisitor) -> f64 {
+impl<R> ExprAccept<R> for LiteralExpr {
+    fn accept(&self, visitor: &mut dyn ExprVisitor<R>) -> R {
         return visitor.visit_literal(self);
     }
 }
 
+impl Expr for LiteralExpr {
+    fn span(&self) -> Span {
+        return self.token.span;
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
 pub struct UnaryExpr {
     op: Token,
     right: Box<dyn Expr>,
@@ -40,12 +69,22 @@ impl UnaryExpr {
     pub fn get_right(&self) -> &Box<dyn Expr> { &self.right }
 }
 
-impl Expr for UnaryExpr {
-    fn accept(&self, visitor: &mut dyn ExprVisitor) -> f64 {
+impl<R> ExprAccept<R> for UnaryExpr {
+    fn accept(&self, visitor: &mut dyn ExprVisitor<R>) -> R {
         return visitor.visit_unary(self);
     }
 }
 
+impl Expr for UnaryExpr {
+    fn span(&self) -> Span {
+        return self.op.span.to(&self.right.span());
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
 pub struct BinaryExpr {
     left: Box<dyn Expr>,
     op: Token,
@@ -61,21 +100,276 @@ impl BinaryExpr {
     pub fn get_right(&self) -> &Box<dyn Expr> { &self.right }
 }
 
-impl Expr for BinaryExpr {
-    fn accept(&self, visitor: &mut dyn ExprVisitor) -> f64 {
+impl<R> ExprAccept<R> for BinaryExpr {
+    fn accept(&self, visitor: &mut dyn ExprVisitor<R>) -> R {
         return visitor.visit_binary(self);
     }
 }
 
-pub trait StmtVisitor {
-    fn visit_expression(&mut self, expression: &ExpressionStmt) -> f64;
-    fn visit_print(&mut self, print: &PrintStmt) -> f64;
+impl Expr for BinaryExpr {
+    fn span(&self) -> Span {
+        return self.left.span().to(&self.right.span());
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct VariableExpr {
+    name: Token,
+}
+
+impl VariableExpr {
+    pub fn new(name: Token) -> Self {
+        Self {  name, }
+    }
+    pub fn get_name(&self) -> &Token { &self.name }
+}
+
+impl<R> ExprAccept<R> for VariableExpr {
+    fn accept(&self, visitor: &mut dyn ExprVisitor<R>) -> R {
+        return visitor.visit_variable(self);
+    }
+}
+
+impl Expr for VariableExpr {
+    fn span(&self) -> Span {
+        return self.name.span;
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct AssignExpr {
+    name: Token,
+    value: Box<dyn Expr>,
+}
+
+impl AssignExpr {
+    pub fn new(name: Token, value: Box<dyn Expr>) -> Self {
+        Self {  name,  value, }
+    }
+    pub fn get_name(&self) -> &Token { &self.name }
+    pub fn get_value(&self) -> &Box<dyn Expr> { &self.value }
+}
+
+impl<R> ExprAccept<R> for AssignExpr {
+    fn accept(&self, visitor: &mut dyn ExprVisitor<R>) -> R {
+        return visitor.visit_assign(self);
+    }
+}
+
+impl Expr for AssignExpr {
+    fn span(&self) -> Span {
+        return self.name.span.to(&self.value.span());
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct LogicalExpr {
+    left: Box<dyn Expr>,
+    op: Token,
+    right: Box<dyn Expr>,
+}
+
+impl LogicalExpr {
+    pub fn new(left: Box<dyn Expr>, op: Token, right: Box<dyn Expr>) -> Self {
+        Self {  left,  op,  right, }
+    }
+    pub fn get_left(&self) -> &Box<dyn Expr> { &self.left }
+    pub fn get_op(&self) -> &Token { &self.op }
+    pub fn get_right(&self) -> &Box<dyn Expr> { &self.right }
+}
+
+impl<R> ExprAccept<R> for LogicalExpr {
+    fn accept(&self, visitor: &mut dyn ExprVisitor<R>) -> R {
+        return visitor.visit_logical(self);
+    }
+}
+
+impl Expr for LogicalExpr {
+    fn span(&self) -> Span {
+        return self.left.span().to(&self.right.span());
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct CallExpr {
+    callee: Box<dyn Expr>,
+    paren: Token,
+    arguments: Vec<Box<dyn Expr>>,
+}
+
+impl CallExpr {
+    pub fn new(callee: Box<dyn Expr>, paren: Token, arguments: Vec<Box<dyn Expr>>) -> Self {
+        Self {  callee,  paren,  arguments, }
+    }
+    pub fn get_callee(&self) -> &Box<dyn Expr> { &self.callee }
+    pub fn get_paren(&self) -> &Token { &self.paren }
+    pub fn get_arguments(&self) -> &Vec<Box<dyn Expr>> { &self.arguments }
+}
+
+impl<R> ExprAccept<R> for CallExpr {
+    fn accept(&self, visitor: &mut dyn ExprVisitor<R>) -> R {
+        return visitor.visit_call(self);
+    }
+}
+
+impl Expr for CallExpr {
+    fn span(&self) -> Span {
+        return self.callee.span().to(&self.paren.span);
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct GetExpr {
+    object: Box<dyn Expr>,
+    name: Token,
+}
+
+impl GetExpr {
+    pub fn new(object: Box<dyn Expr>, name: Token) -> Self {
+        Self {  object,  name, }
+    }
+    pub fn get_object(&self) -> &Box<dyn Expr> { &self.object }
+    pub fn get_name(&self) -> &Token { &self.name }
+}
+
+impl<R> ExprAccept<R> for GetExpr {
+    fn accept(&self, visitor: &mut dyn ExprVisitor<R>) -> R {
+        return visitor.visit_get(self);
+    }
+}
+
+impl Expr for GetExpr {
+    fn span(&self) -> Span {
+        return self.object.span().to(&self.name.span);
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct SetExpr {
+    target: GetExpr,
+    value: Box<dyn Expr>,
+}
+
+impl SetExpr {
+    pub fn new(target: GetExpr, value: Box<dyn Expr>) -> Self {
+        Self {  target,  value, }
+    }
+    pub fn get_target(&self) -> &GetExpr { &self.target }
+    pub fn get_value(&self) -> &Box<dyn Expr> { &self.value }
+}
+
+impl<R> ExprAccept<R> for SetExpr {
+    fn accept(&self, visitor: &mut dyn ExprVisitor<R>) -> R {
+        return visitor.visit_set(self);
+    }
+}
+
+impl Expr for SetExpr {
+    fn span(&self) -> Span {
+        return self.target.span().to(&self.value.span());
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct ThisExpr {
+    keyword: Token,
+}
+
+impl ThisExpr {
+    pub fn new(keyword: Token) -> Self {
+        Self {  keyword, }
+    }
+    pub fn get_keyword(&self) -> &Token { &self.keyword }
+}
+
+impl<R> ExprAccept<R> for ThisExpr {
+    fn accept(&self, visitor: &mut dyn ExprVisitor<R>) -> R {
+        return visitor.visit_this(self);
+    }
+}
+
+impl Expr for ThisExpr {
+    fn span(&self) -> Span {
+        return self.keyword.span;
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct SuperExpr {
+    keyword: Token,
+    method: Token,
+}
+
+impl SuperExpr {
+    pub fn new(keyword: Token, method: Token) -> Self {
+        Self {  keyword,  method, }
+    }
+    pub fn get_keyword(&self) -> &Token { &self.keyword }
+    pub fn get_method(&self) -> &Token { &self.method }
+}
+
+impl<R> ExprAccept<R> for SuperExpr {
+    fn accept(&self, visitor: &mut dyn ExprVisitor<R>) -> R {
+        return visitor.visit_super(self);
+    }
+}
+
+impl Expr for SuperExpr {
+    fn span(&self) -> Span {
+        return self.keyword.span.to(&self.method.span);
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+pub trait StmtVisitor<R> {
+    fn visit_expression(&mut self, stmt: &ExpressionStmt) -> R;
+    fn visit_print(&mut self, stmt: &PrintStmt) -> R;
+    fn visit_var(&mut self, stmt: &VarStmt) -> R;
+    fn visit_block(&mut self, stmt: &BlockStmt) -> R;
+    fn visit_if(&mut self, stmt: &IfStmt) -> R;
+    fn visit_while(&mut self, stmt: &WhileStmt) -> R;
+    fn visit_for(&mut self, stmt: &ForStmt) -> R;
+    fn visit_function(&mut self, stmt: &FunctionStmt) -> R;
+    fn visit_return(&mut self, stmt: &ReturnStmt) -> R;
+    fn visit_class(&mut self, stmt: &ClassStmt) -> R;
+}
+
+pub trait StmtAccept<R> {
+    fn accept(&self, visitor: &mut dyn StmtVisitor<R>) -> R;
 }
 
-pub trait Stmt {
-    fn accept(&self, visitor: &mut dyn StmtVisitor) -> f64;
+pub trait Stmt: StmtAccept<Result<(), Unwind>> + StmtAccept<()> + StmtAccept<String> + Debug {
+    fn span(&self) -> Span;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
 }
 
+#[derive(Debug)]
 pub struct ExpressionStmt {
     expression: Box<dyn Expr>,
 }
@@ -87,26 +381,303 @@ impl ExpressionStmt {
     pub fn get_expression(&self) -> &Box<dyn Expr> { &self.expression }
 }
 
-impl Stmt for ExpressionStmt {
-    fn accept(&self, visitor: &mut dyn StmtVisitor) -> f64 {
+impl<R> StmtAccept<R> for ExpressionStmt {
+    fn accept(&self, visitor: &mut dyn StmtVisitor<R>) -> R {
         return visitor.visit_expression(self);
     }
 }
 
+impl Stmt for ExpressionStmt {
+    fn span(&self) -> Span {
+        return self.expression.span();
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
 pub struct PrintStmt {
+    keyword: Token,
     expression: Box<dyn Expr>,
 }
 
 impl PrintStmt {
-    pub fn new(expression: Box<dyn Expr>) -> Self {
-        Self {  expression, }
+    pub fn new(keyword: Token, expression: Box<dyn Expr>) -> Self {
+        Self {  keyword,  expression, }
     }
+    pub fn get_keyword(&self) -> &Token { &self.keyword }
     pub fn get_expression(&self) -> &Box<dyn Expr> { &self.expression }
 }
 
-impl Stmt for PrintStmt {
-    fn accept(&self, visitor: &mut dyn StmtVisitor) -> f64 {
+impl<R> StmtAccept<R> for PrintStmt {
+    fn accept(&self, visitor: &mut dyn StmtVisitor<R>) -> R {
         return visitor.visit_print(self);
     }
 }
 
+impl Stmt for PrintStmt {
+    fn span(&self) -> Span {
+        return self.keyword.span.to(&self.expression.span());
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct VarStmt {
+    name: Token,
+    initializer: Option<Box<dyn Expr>>,
+    doc: Option<String>,
+}
+
+impl VarStmt {
+    pub fn new(name: Token, initializer: Option<Box<dyn Expr>>, doc: Option<String>) -> Self {
+        Self {  name,  initializer,  doc, }
+    }
+    pub fn get_name(&self) -> &Token { &self.name }
+    pub fn get_initializer(&self) -> &Option<Box<dyn Expr>> { &self.initializer }
+    pub fn get_doc(&self) -> &Option<String> { &self.doc }
+}
+
+impl<R> StmtAccept<R> for VarStmt {
+    fn accept(&self, visitor: &mut dyn StmtVisitor<R>) -> R {
+        return visitor.visit_var(self);
+    }
+}
+
+impl Stmt for VarStmt {
+    fn span(&self) -> Span {
+        return self.initializer.as_ref().map_or(self.name.span, |initializer| self.name.span.to(&initializer.span()));
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct BlockStmt {
+    left_brace: Token,
+    statements: Vec<Box<dyn Stmt>>,
+    right_brace: Token,
+}
+
+impl BlockStmt {
+    pub fn new(left_brace: Token, statements: Vec<Box<dyn Stmt>>, right_brace: Token) -> Self {
+        Self {  left_brace,  statements,  right_brace, }
+    }
+    pub fn get_left_brace(&self) -> &Token { &self.left_brace }
+    pub fn get_statements(&self) -> &Vec<Box<dyn Stmt>> { &self.statements }
+    pub fn get_right_brace(&self) -> &Token { &self.right_brace }
+}
+
+impl<R> StmtAccept<R> for BlockStmt {
+    fn accept(&self, visitor: &mut dyn StmtVisitor<R>) -> R {
+        return visitor.visit_block(self);
+    }
+}
+
+impl Stmt for BlockStmt {
+    fn span(&self) -> Span {
+        return self.left_brace.span.to(&self.right_brace.span);
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct IfStmt {
+    keyword: Token,
+    condition: Box<dyn Expr>,
+    then_branch: Box<dyn Stmt>,
+    else_branch: Option<Box<dyn Stmt>>,
+}
+
+impl IfStmt {
+    pub fn new(keyword: Token, condition: Box<dyn Expr>, then_branch: Box<dyn Stmt>, else_branch: Option<Box<dyn Stmt>>) -> Self {
+        Self {  keyword,  condition,  then_branch,  else_branch, }
+    }
+    pub fn get_keyword(&self) -> &Token { &self.keyword }
+    pub fn get_condition(&self) -> &Box<dyn Expr> { &self.condition }
+    pub fn get_then_branch(&self) -> &Box<dyn Stmt> { &self.then_branch }
+    pub fn get_else_branch(&self) -> &Option<Box<dyn Stmt>> { &self.else_branch }
+}
+
+impl<R> StmtAccept<R> for IfStmt {
+    fn accept(&self, visitor: &mut dyn StmtVisitor<R>) -> R {
+        return visitor.visit_if(self);
+    }
+}
+
+impl Stmt for IfStmt {
+    fn span(&self) -> Span {
+        return self.keyword.span.to(&self.else_branch.as_ref().unwrap_or(&self.then_branch).span());
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct WhileStmt {
+    keyword: Token,
+    condition: Box<dyn Expr>,
+    body: Box<dyn Stmt>,
+}
+
+impl WhileStmt {
+    pub fn new(keyword: Token, condition: Box<dyn Expr>, body: Box<dyn Stmt>) -> Self {
+        Self {  keyword,  condition,  body, }
+    }
+    pub fn get_keyword(&self) -> &Token { &self.keyword }
+    pub fn get_condition(&self) -> &Box<dyn Expr> { &self.condition }
+    pub fn get_body(&self) -> &Box<dyn Stmt> { &self.body }
+}
+
+impl<R> StmtAccept<R> for WhileStmt {
+    fn accept(&self, visitor: &mut dyn StmtVisitor<R>) -> R {
+        return visitor.visit_while(self);
+    }
+}
+
+impl Stmt for WhileStmt {
+    fn span(&self) -> Span {
+        return self.keyword.span.to(&self.body.span());
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct ForStmt {
+    keyword: Token,
+    initializer: Option<Box<dyn Stmt>>,
+    condition: Option<Box<dyn Expr>>,
+    increment: Option<Box<dyn Expr>>,
+    body: Box<dyn Stmt>,
+}
+
+impl ForStmt {
+    pub fn new(keyword: Token, initializer: Option<Box<dyn Stmt>>, condition: Option<Box<dyn Expr>>, increment: Option<Box<dyn Expr>>, body: Box<dyn Stmt>) -> Self {
+        Self {  keyword,  initializer,  condition,  increment,  body, }
+    }
+    pub fn get_keyword(&self) -> &Token { &self.keyword }
+    pub fn get_initializer(&self) -> &Option<Box<dyn Stmt>> { &self.initializer }
+    pub fn get_condition(&self) -> &Option<Box<dyn Expr>> { &self.condition }
+    pub fn get_increment(&self) -> &Option<Box<dyn Expr>> { &self.increment }
+    pub fn get_body(&self) -> &Box<dyn Stmt> { &self.body }
+}
+
+impl<R> StmtAccept<R> for ForStmt {
+    fn accept(&self, visitor: &mut dyn StmtVisitor<R>) -> R {
+        return visitor.visit_for(self);
+    }
+}
+
+impl Stmt for ForStmt {
+    fn span(&self) -> Span {
+        return self.keyword.span.to(&self.body.span());
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct FunctionStmt {
+    name: Token,
+    params: Vec<Token>,
+    body: Rc<BlockStmt>,
+    doc: Option<String>,
+}
+
+impl FunctionStmt {
+    pub fn new(name: Token, params: Vec<Token>, body: Rc<BlockStmt>, doc: Option<String>) -> Self {
+        Self {  name,  params,  body,  doc, }
+    }
+    pub fn get_name(&self) -> &Token { &self.name }
+    pub fn get_params(&self) -> &Vec<Token> { &self.params }
+    pub fn get_body(&self) -> &Rc<BlockStmt> { &self.body }
+    pub fn get_doc(&self) -> &Option<String> { &self.doc }
+}
+
+impl<R> StmtAccept<R> for FunctionStmt {
+    fn accept(&self, visitor: &mut dyn StmtVisitor<R>) -> R {
+        return visitor.visit_function(self);
+    }
+}
+
+impl Stmt for FunctionStmt {
+    fn span(&self) -> Span {
+        return self.name.span.to(&self.body.span());
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct ReturnStmt {
+    keyword: Token,
+    value: Option<Box<dyn Expr>>,
+}
+
+impl ReturnStmt {
+    pub fn new(keyword: Token, value: Option<Box<dyn Expr>>) -> Self {
+        Self {  keyword,  value, }
+    }
+    pub fn get_keyword(&self) -> &Token { &self.keyword }
+    pub fn get_value(&self) -> &Option<Box<dyn Expr>> { &self.value }
+}
+
+impl<R> StmtAccept<R> for ReturnStmt {
+    fn accept(&self, visitor: &mut dyn StmtVisitor<R>) -> R {
+        return visitor.visit_return(self);
+    }
+}
+
+impl Stmt for ReturnStmt {
+    fn span(&self) -> Span {
+        return self.value.as_ref().map_or(self.keyword.span, |value| self.keyword.span.to(&value.span()));
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
+#[derive(Debug)]
+pub struct ClassStmt {
+    name: Token,
+    superclass: Option<VariableExpr>,
+    methods: Vec<FunctionStmt>,
+    doc: Option<String>,
+}
+
+impl ClassStmt {
+    pub fn new(name: Token, superclass: Option<VariableExpr>, methods: Vec<FunctionStmt>, doc: Option<String>) -> Self {
+        Self {  name,  superclass,  methods,  doc, }
+    }
+    pub fn get_name(&self) -> &Token { &self.name }
+    pub fn get_superclass(&self) -> &Option<VariableExpr> { &self.superclass }
+    pub fn get_methods(&self) -> &Vec<FunctionStmt> { &self.methods }
+    pub fn get_doc(&self) -> &Option<String> { &self.doc }
+}
+
+impl<R> StmtAccept<R> for ClassStmt {
+    fn accept(&self, visitor: &mut dyn StmtVisitor<R>) -> R {
+        return visitor.visit_class(self);
+    }
+}
+
+impl Stmt for ClassStmt {
+    fn span(&self) -> Span {
+        return self.methods.last().map_or(self.name.span, |method| self.name.span.to(&method.span()));
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        return self;
+    }
+}
+
@@ -0,0 +1,103 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::class::Instance;
+use crate::environment::Environment;
+use crate::expr::{BlockStmt, FunctionStmt};
+use crate::interpreter::{Interpreter, RuntimeError, Unwind};
+use crate::token::Token;
+use crate::value::Value;
+
+pub trait Callable {
+    fn arity(&self) -> usize;
+    fn call(&self, interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError>;
+}
+
+// A user-defined function together with the environment it was declared in.
+pub struct Function {
+    name: Token,
+    params: Vec<Token>,
+    body: Rc<BlockStmt>,
+    closure: Rc<RefCell<Environment>>,
+    is_initializer: bool,
+}
+
+impl Function {
+    pub fn new(declaration: &FunctionStmt, closure: Rc<RefCell<Environment>>, is_initializer: bool) -> Function {
+        Function {
+            name: declaration.get_name().clone(),
+            params: declaration.get_params().clone(),
+            body: Rc::clone(declaration.get_body()),
+            closure,
+            is_initializer,
+        }
+    }
+
+    pub fn get_name(&self) -> &Token { &self.name }
+
+    // The same method with `this` bound to `instance`.
+    pub fn bind(&self, instance: Rc<RefCell<Instance>>) -> Function {
+        let mut environment = Environment::with_enclosing(Rc::clone(&self.closure));
+        environment.define("this", Value::Instance(instance));
+        Function {
+            name: self.name.clone(),
+            params: self.params.clone(),
+            body: Rc::clone(&self.body),
+            closure: Rc::new(RefCell::new(environment)),
+            is_initializer: self.is_initializer,
+        }
+    }
+
+    fn this(&self) -> Value {
+        return self.closure.borrow().lookup("this").unwrap_or(Value::Nil);
+    }
+}
+
+impl Callable for Function {
+    fn arity(&self) -> usize {
+        return self.params.len();
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut environment = Environment::with_enclosing(Rc::clone(&self.closure));
+        for (param, argument) in self.params.iter().zip(arguments) {
+            environment.define(&param.literal, argument);
+        }
+        match interpreter.execute_block(self.body.get_statements(), environment) {
+            // An initializer always evaluates to the instance being built.
+            Ok(()) | Err(Unwind::Return(_)) if self.is_initializer => return Ok(self.this()),
+            Ok(()) => return Ok(Value::Nil),
+            Err(Unwind::Return(value)) => return Ok(value),
+            Err(Unwind::Error(error)) => return Err(error),
+        }
+    }
+}
+
+// A function implemented in Rust and made available to scripts as a global.
+pub struct NativeFunction {
+    name: String,
+    arity: usize,
+    function: Box<dyn Fn(Vec<Value>) -> Value>,
+}
+
+impl NativeFunction {
+    pub fn new(name: &str, arity: usize, function: Box<dyn Fn(Vec<Value>) -> Value>) -> NativeFunction {
+        NativeFunction {
+            name: name.to_owned(),
+            arity,
+            function,
+        }
+    }
+
+    pub fn get_name(&self) -> &str { &self.name }
+}
+
+impl Callable for NativeFunction {
+    fn arity(&self) -> usize {
+        return self.arity;
+    }
+
+    fn call(&self, _: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        return Ok((self.function)(arguments));
+    }
+}
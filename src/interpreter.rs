@@ -1,69 +1,473 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
 use crate::expr::*;
 use crate::token::*;
+use crate::value::Value;
+use crate::environment::Environment;
+use crate::function::Function;
+use crate::class::{Class, Instance};
+use crate::diagnostic::{Code, Diagnostic, Label};
+
+// Statements and expressions being run can only nest this deep, counting
+// those of every call in progress, before it's reported as a runtime error
+// instead of overflowing the native stack. The check is made where nesting
+// isn't already bounded by the parser: at calls and in operator chains.
+const MAX_DEPTH: usize = 20_000;
+
+// The token is boxed to keep results that carry an error small.
+#[derive(Debug,Clone)]
+pub struct RuntimeError {
+    pub code: Code,
+    pub token: Box<Token>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl RuntimeError {
+    pub fn new(code: Code, token: Token, message: String) -> RuntimeError {
+        RuntimeError {
+            code,
+            token: Box::new(token),
+            message,
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
 
-pub struct Interpreter {}
+    pub fn with_label(mut self, span: Span, message: String) -> RuntimeError {
+        self.labels.push(Label::new(span, message));
+        return self;
+    }
+
+    pub fn with_note(mut self, note: String) -> RuntimeError {
+        self.notes.push(note);
+        return self;
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        return Diagnostic::error(self.code, self.token.span, self.message.clone())
+            .with_labels(&self.labels)
+            .with_notes(&self.notes);
+    }
+}
+
+impl std::fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[{}] Runtime error at '{}': {}", self.token.span, self.token.literal, self.message)
+    }
+}
+
+// Why execution of a statement stopped before reaching its end.
+#[derive(Debug)]
+pub enum Unwind {
+    Error(RuntimeError),
+    Return(Value),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(error: RuntimeError) -> Unwind {
+        Unwind::Error(error)
+    }
+}
+
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    // Scope distance of each resolved local variable reference, keyed by the
+    // span of the referencing token. Unresolved names are globals.
+    locals: HashMap<Span, usize>,
+    depth: usize,
+}
 
 impl Interpreter {
 
     pub fn new() -> Self {
-        Self {}
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        Self {
+            environment: Rc::clone(&globals),
+            globals,
+            locals: HashMap::new(),
+            depth: 0,
+        }
+    }
+
+    // Current value of a global variable.
+    pub fn global(&self, name: &str) -> Option<Value> {
+        return self.globals.borrow().lookup(name);
+    }
+
+    pub fn define_global(&mut self, name: &str, value: Value) {
+        self.globals.borrow_mut().define(name, value);
+    }
+
+    pub fn global_names(&self) -> Vec<String> {
+        return self.globals.borrow().names();
+    }
+
+    // Called by the resolver for every local variable reference.
+    pub fn resolve(&mut self, name: &Token, depth: usize) {
+        self.locals.insert(name.span, depth);
     }
 
-    pub fn interpret(&mut self, statements: &Vec<Box<dyn Stmt>>) {
+    pub fn interpret(&mut self, statements: &Vec<Box<dyn Stmt>>) -> Result<(), RuntimeError> {
         for statement in statements {
-            self.execute(statement);
+            match self.execute(statement) {
+                Ok(()) => {}
+                // A top-level `return` ends the program.
+                Err(Unwind::Return(_)) => return Ok(()),
+                Err(Unwind::Error(error)) => return Err(error),
+            }
         }
+        return Ok(());
     }
 
-    fn execute(&mut self, stmt: &Box<dyn Stmt>) {
-        stmt.accept(self); 
+    fn execute(&mut self, stmt: &Box<dyn Stmt>) -> Result<(), Unwind> {
+        self.depth += 1;
+        let result = stmt.accept(self);
+        self.depth -= 1;
+        return result;
     }
 
-    fn evaluate(&mut self, expr: &Box<dyn Expr>) -> f64 {
-        return expr.accept(self);
+    pub fn interpret_expression(&mut self, expr: &Box<dyn Expr>) -> Result<Value, RuntimeError> {
+        return self.evaluate(expr);
+    }
+
+    // Runs `statements` in `environment`, restoring the current environment
+    // afterwards even if one of them fails.
+    pub fn execute_block(&mut self, statements: &Vec<Box<dyn Stmt>>, environment: Environment) -> Result<(), Unwind> {
+        let previous = std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+        let result = statements.iter().try_for_each(|statement| self.execute(statement));
+        self.environment = previous;
+        return result;
+    }
+
+    fn execute_for(&mut self, stmt: &ForStmt) -> Result<(), Unwind> {
+        if let Some(initializer) = stmt.get_initializer() {
+            self.execute(initializer)?;
+        }
+        loop {
+            if let Some(condition) = stmt.get_condition() {
+                if !self.evaluate(condition)?.is_truthy() {
+                    return Ok(());
+                }
+            }
+            self.execute(stmt.get_body())?;
+            if let Some(increment) = stmt.get_increment() {
+                self.evaluate(increment)?;
+            }
+        }
+    }
+
+    fn evaluate(&mut self, expr: &Box<dyn Expr>) -> Result<Value, RuntimeError> {
+        self.depth += 1;
+        let result = expr.accept(self);
+        self.depth -= 1;
+        return result;
+    }
+
+    // Fails at `token` if going any deeper would pass `MAX_DEPTH`.
+    fn check_depth(&self, token: &Token) -> Result<(), RuntimeError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(RuntimeError::new(Code::StackOverflow, token.clone(), "Stack overflow".to_owned())
+                .with_note(format!("Calls, statements and expressions can only nest {} deep", MAX_DEPTH)));
+        }
+        return Ok(());
+    }
+
+    fn look_up_variable(&self, name: &Token) -> Result<Value, RuntimeError> {
+        match self.locals.get(&name.span) {
+            Some(distance) => return Environment::ancestor(&self.environment, *distance).borrow().get(name),
+            None => return self.globals.borrow().get(name)
+        }
+    }
+
+    fn number_operands(&self, binary: &BinaryExpr, left: &Value, right: &Value) -> Result<(f64, f64), RuntimeError> {
+        match (left, right) {
+            (Value::Number(left), Value::Number(right)) => return Ok((*left, *right)),
+            _ => return Err(RuntimeError::new(Code::OperandType, binary.get_op().clone(),
+                    format!("Operands must be numbers, found {} and {}", left.type_name(), right.type_name()))
+                .with_label(binary.get_left().span(), left.type_name().to_owned())
+                .with_label(binary.get_right().span(), right.type_name().to_owned()))
+        }
     }
 }
 
-impl StmtVisitor for Interpreter {
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StmtVisitor<Result<(), Unwind>> for Interpreter {
+
+    fn visit_expression(&mut self, stmt: &ExpressionStmt) -> Result<(), Unwind> {
+        self.evaluate(stmt.get_expression())?;
+        return Ok(());
+    }
 
-    fn visit_expression(&mut self, stmt: &ExpressionStmt) -> f64 {
-        self.evaluate(stmt.get_expression());
-        return 0.0;
+    fn visit_print(&mut self, stmt: &PrintStmt) -> Result<(), Unwind> {
+        let value = self.evaluate(stmt.get_expression())?;
+        println!("{}", value);
+        return Ok(());
     }
 
-    fn visit_print(&mut self, stmt: &PrintStmt) -> f64 { 
-        let value = self.evaluate(stmt.get_expression());
-        print!("{}", value);
-        return 0.0; 
+    fn visit_var(&mut self, stmt: &VarStmt) -> Result<(), Unwind> {
+        let value = match stmt.get_initializer() {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => Value::Nil,
+        };
+        self.environment.borrow_mut().define(&stmt.get_name().literal, value);
+        return Ok(());
+    }
+
+    fn visit_block(&mut self, stmt: &BlockStmt) -> Result<(), Unwind> {
+        let environment = Environment::with_enclosing(Rc::clone(&self.environment));
+        return self.execute_block(stmt.get_statements(), environment);
+    }
+
+    fn visit_if(&mut self, stmt: &IfStmt) -> Result<(), Unwind> {
+        if self.evaluate(stmt.get_condition())?.is_truthy() {
+            return self.execute(stmt.get_then_branch());
+        }
+        if let Some(else_branch) = stmt.get_else_branch() {
+            return self.execute(else_branch);
+        }
+        return Ok(());
+    }
+
+    fn visit_while(&mut self, stmt: &WhileStmt) -> Result<(), Unwind> {
+        while self.evaluate(stmt.get_condition())?.is_truthy() {
+            self.execute(stmt.get_body())?;
+        }
+        return Ok(());
+    }
+
+    fn visit_for(&mut self, stmt: &ForStmt) -> Result<(), Unwind> {
+        // The loop variable lives in its own scope around the whole loop.
+        let environment = Environment::with_enclosing(Rc::clone(&self.environment));
+        let previous = std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+        let result = self.execute_for(stmt);
+        self.environment = previous;
+        return result;
+    }
+
+    fn visit_function(&mut self, stmt: &FunctionStmt) -> Result<(), Unwind> {
+        let function = Function::new(stmt, Rc::clone(&self.environment), false);
+        self.environment.borrow_mut().define(&stmt.get_name().literal, Value::Function(Rc::new(function)));
+        return Ok(());
+    }
+
+    fn visit_return(&mut self, stmt: &ReturnStmt) -> Result<(), Unwind> {
+        let value = match stmt.get_value() {
+            Some(value) => self.evaluate(value)?,
+            None => Value::Nil,
+        };
+        return Err(Unwind::Return(value));
+    }
+
+    fn visit_class(&mut self, stmt: &ClassStmt) -> Result<(), Unwind> {
+        let superclass = match stmt.get_superclass() {
+            Some(superclass) => match self.visit_variable(superclass)? {
+                Value::Class(class) => Some(class),
+                _ => return Err(Unwind::Error(RuntimeError::new(Code::InvalidSuperclass, superclass.get_name().clone(),
+                        "Superclass must be a class".to_owned())))
+            },
+            None => None,
+        };
+        let name = stmt.get_name();
+        self.environment.borrow_mut().define(&name.literal, Value::Nil);
+
+        // Methods of a subclass close over an extra scope holding `super`.
+        let mut environment = Rc::clone(&self.environment);
+        if let Some(superclass) = &superclass {
+            let mut scope = Environment::with_enclosing(environment);
+            scope.define("super", Value::Class(Rc::clone(superclass)));
+            environment = Rc::new(RefCell::new(scope));
+        }
+        let mut methods = HashMap::new();
+        for method in stmt.get_methods() {
+            let method_name = &method.get_name().literal;
+            let function = Function::new(method, Rc::clone(&environment), method_name == "init");
+            methods.insert(method_name.clone(), Rc::new(function));
+        }
+
+        let class = Class::new(name.clone(), superclass, methods);
+        self.environment.borrow_mut().assign(name, Value::Class(Rc::new(class)))?;
+        return Ok(());
     }
 }
 
-impl ExprVisitor for Interpreter {
+impl ExprVisitor<Result<Value, RuntimeError>> for Interpreter {
 
-    fn visit_literal(&mut self, literal: &LiteralExpr) -> f64 {
-        return literal.get_token().literal.parse::<f64>().unwrap();
+    fn visit_literal(&mut self, literal: &LiteralExpr) -> Result<Value, RuntimeError> {
+        let token = literal.get_token();
+        match token.token_type {
+            TokenType::NumberLiteral => match token.number {
+                Some(value) => return Ok(Value::Number(value)),
+                None => return Err(RuntimeError::new(Code::Internal, token.clone(), "Invalid number literal".to_owned()))
+            },
+            TokenType::StringLiteral => match &token.string {
+                Some(value) => return Ok(Value::Str(value.clone())),
+                None => return Err(RuntimeError::new(Code::Internal, token.clone(), "Invalid string literal".to_owned()))
+            },
+            TokenType::True => return Ok(Value::Bool(true)),
+            TokenType::False => return Ok(Value::Bool(false)),
+            TokenType::Nil => return Ok(Value::Nil),
+            _ => return Err(RuntimeError::new(Code::Internal, token.clone(), "Unsupported literal".to_owned()))
+        }
     }
 
-    fn visit_unary(&mut self, unary: &UnaryExpr) -> f64 {
+    fn visit_unary(&mut self, unary: &UnaryExpr) -> Result<Value, RuntimeError> {
         let op = unary.get_op();
-        let right = unary.get_right().accept(self);
-        match op.token_type {
-            TokenType::Plus => return right,
-            TokenType::Minus => return -right,
-            _ => panic!("Unexpected token: {:?}", op.token_type)
+        let right = self.evaluate(unary.get_right())?;
+        match (&op.token_type, right) {
+            (TokenType::Plus, Value::Number(right)) => return Ok(Value::Number(right)),
+            (TokenType::Minus, Value::Number(right)) => return Ok(Value::Number(-right)),
+            (TokenType::Bang, right) => return Ok(Value::Bool(!right.is_truthy())),
+            (TokenType::Plus | TokenType::Minus, right) => return Err(RuntimeError::new(Code::OperandType, op.clone(),
+                    format!("Operand must be a number, found {}", right.type_name()))
+                .with_label(unary.get_right().span(), right.type_name().to_owned())),
+            _ => return Err(RuntimeError::new(Code::Internal, op.clone(), "Unsupported unary operator".to_owned()))
         }
     }
 
-    fn visit_binary(&mut self, binary: &BinaryExpr) -> f64 {
-        let left = binary.get_left().accept(self);
-        let right = binary.get_right().accept(self);
+    fn visit_binary(&mut self, binary: &BinaryExpr) -> Result<Value, RuntimeError> {
+        self.check_depth(binary.get_op())?;
+        let left = self.evaluate(binary.get_left())?;
+        let right = self.evaluate(binary.get_right())?;
         let op = binary.get_op();
         match op.token_type {
-            TokenType::Plus => return left + right,
-            TokenType::Minus => return left - right,
-            TokenType::Star => return left * right,
-            TokenType::Slash => return left / right,
-            _ => panic!("Unexpected token: {:?}", op.token_type)
+            TokenType::Plus => match (left, right) {
+                (Value::Number(left), Value::Number(right)) => return Ok(Value::Number(left + right)),
+                (Value::Str(left), Value::Str(right)) => return Ok(Value::Str(left + &right)),
+                (left, right) => return Err(RuntimeError::new(Code::OperandType, op.clone(),
+                        format!("Operands must be two numbers or two strings, found {} and {}", left.type_name(), right.type_name()))
+                    .with_label(binary.get_left().span(), left.type_name().to_owned())
+                    .with_label(binary.get_right().span(), right.type_name().to_owned()))
+            },
+            TokenType::Minus => {
+                let (left, right) = self.number_operands(binary, &left, &right)?;
+                return Ok(Value::Number(left - right));
+            }
+            TokenType::Star => {
+                let (left, right) = self.number_operands(binary, &left, &right)?;
+                return Ok(Value::Number(left * right));
+            }
+            TokenType::Slash => {
+                let (left, right) = self.number_operands(binary, &left, &right)?;
+                return Ok(Value::Number(left / right));
+            }
+            TokenType::EqualEqual => return Ok(Value::Bool(left == right)),
+            TokenType::BangEqual => return Ok(Value::Bool(left != right)),
+            TokenType::Less => {
+                let (left, right) = self.number_operands(binary, &left, &right)?;
+                return Ok(Value::Bool(left < right));
+            }
+            TokenType::LessEqual => {
+                let (left, right) = self.number_operands(binary, &left, &right)?;
+                return Ok(Value::Bool(left <= right));
+            }
+            TokenType::Greater => {
+                let (left, right) = self.number_operands(binary, &left, &right)?;
+                return Ok(Value::Bool(left > right));
+            }
+            TokenType::GreaterEqual => {
+                let (left, right) = self.number_operands(binary, &left, &right)?;
+                return Ok(Value::Bool(left >= right));
+            }
+            _ => return Err(RuntimeError::new(Code::Internal, op.clone(), "Unsupported binary operator".to_owned()))
+        }
+    }
+
+    fn visit_variable(&mut self, variable: &VariableExpr) -> Result<Value, RuntimeError> {
+        return self.look_up_variable(variable.get_name());
+    }
+
+    fn visit_assign(&mut self, assign: &AssignExpr) -> Result<Value, RuntimeError> {
+        let value = self.evaluate(assign.get_value())?;
+        let name = assign.get_name();
+        match self.locals.get(&name.span) {
+            Some(distance) => Environment::ancestor(&self.environment, *distance).borrow_mut().assign(name, value.clone())?,
+            None => self.globals.borrow_mut().assign(name, value.clone())?,
+        }
+        return Ok(value);
+    }
+
+    fn visit_logical(&mut self, logical: &LogicalExpr) -> Result<Value, RuntimeError> {
+        self.check_depth(logical.get_op())?;
+        let left = self.evaluate(logical.get_left())?;
+        match logical.get_op().token_type {
+            TokenType::Or if left.is_truthy() => return Ok(left),
+            TokenType::And if !left.is_truthy() => return Ok(left),
+            _ => return self.evaluate(logical.get_right())
+        }
+    }
+
+    fn visit_call(&mut self, call: &CallExpr) -> Result<Value, RuntimeError> {
+        self.check_depth(call.get_paren())?;
+        let callee = self.evaluate(call.get_callee())?;
+        let mut arguments = Vec::new();
+        for argument in call.get_arguments() {
+            arguments.push(self.evaluate(argument)?);
+        }
+        let paren = call.get_paren();
+        let callable = match callee.as_callable() {
+            Some(callable) => callable,
+            None => return Err(RuntimeError::new(Code::NotCallable, paren.clone(),
+                    format!("Can only call functions and classes, found {}", callee.type_name())))
+        };
+        if arguments.len() != callable.arity() {
+            return Err(RuntimeError::new(Code::ArityMismatch, paren.clone(),
+                    format!("Expected {} arguments but got {}", callable.arity(), arguments.len())));
+        }
+        return callable.call(self, arguments);
+    }
+
+    fn visit_get(&mut self, get: &GetExpr) -> Result<Value, RuntimeError> {
+        self.check_depth(get.get_name())?;
+        let object = self.evaluate(get.get_object())?;
+        match object {
+            Value::Instance(instance) => return Instance::get(&instance, get.get_name()),
+            _ => return Err(RuntimeError::new(Code::NotAnInstance, get.get_name().clone(),
+                    format!("Only instances have properties, found {}", object.type_name())))
+        }
+    }
+
+    fn visit_set(&mut self, set: &SetExpr) -> Result<Value, RuntimeError> {
+        let target = set.get_target();
+        let object = self.evaluate(target.get_object())?;
+        let instance = match object {
+            Value::Instance(instance) => instance,
+            _ => return Err(RuntimeError::new(Code::NotAnInstance, target.get_name().clone(),
+                    format!("Only instances have fields, found {}", object.type_name())))
+        };
+        let value = self.evaluate(set.get_value())?;
+        instance.borrow_mut().set(target.get_name(), value.clone());
+        return Ok(value);
+    }
+
+    fn visit_this(&mut self, this: &ThisExpr) -> Result<Value, RuntimeError> {
+        return self.look_up_variable(this.get_keyword());
+    }
+
+    fn visit_super(&mut self, sup: &SuperExpr) -> Result<Value, RuntimeError> {
+        // `this` is bound in the scope just inside the one holding `super`.
+        let distance = self.locals.get(&sup.get_keyword().span).copied().unwrap_or(0);
+        let superclass = match Environment::ancestor(&self.environment, distance).borrow().lookup("super") {
+            Some(Value::Class(superclass)) => superclass,
+            _ => return Err(RuntimeError::new(Code::Internal, sup.get_keyword().clone(), "Can't use 'super' outside of a subclass".to_owned()))
+        };
+        let instance = match Environment::ancestor(&self.environment, distance.saturating_sub(1)).borrow().lookup("this") {
+            Some(Value::Instance(instance)) => instance,
+            _ => return Err(RuntimeError::new(Code::Internal, sup.get_keyword().clone(), "Can't use 'super' outside of a method".to_owned()))
+        };
+        let method = sup.get_method();
+        match superclass.find_method(&method.literal) {
+            Some(function) => return Ok(Value::Function(Rc::new(function.bind(instance)))),
+            None => return Err(RuntimeError::new(Code::UndefinedProperty, method.clone(), format!("Undefined property '{}'", method.literal)))
         }
     }
 }
@@ -1,86 +1,192 @@
-use crate::token::{Token, TokenType};
+use crate::token::{Token, TokenType, Span};
+use crate::diagnostic::{Code, Diagnostic};
 
+pub const KEYWORDS: [(&str, TokenType); 16] = [
+    ("and", TokenType::And),
+    ("class", TokenType::Class),
+    ("else", TokenType::Else),
+    ("false", TokenType::False),
+    ("fun", TokenType::Fun),
+    ("for", TokenType::For),
+    ("if", TokenType::If),
+    ("nil", TokenType::Nil),
+    ("or", TokenType::Or),
+    ("print", TokenType::Print),
+    ("return", TokenType::Return),
+    ("super", TokenType::Super),
+    ("this", TokenType::This),
+    ("true", TokenType::True),
+    ("var", TokenType::Var),
+    ("while", TokenType::While),
+];
+
+#[derive(Debug,Clone)]
+pub struct LexError {
+    pub code: Code,
+    pub span: Span,
+    pub message: String,
+    pub help: Option<String>,
+}
+
+impl LexError {
+    pub fn new(code: Code, span: Span, message: String) -> LexError {
+        LexError {
+            code,
+            span,
+            message,
+            help: None,
+        }
+    }
+
+    pub fn with_help(mut self, help: &str) -> LexError {
+        self.help = Some(help.to_owned());
+        return self;
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        return Diagnostic::error(self.code, self.span, self.message.clone()).with_help(self.help.clone());
+    }
+}
+
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[{}] Error: {}", self.span, self.message)
+    }
+}
+
+// Splits source text into tokens in a single pass. `start` and `current`
+// are byte offsets into `input`, always on char boundaries. Malformed input
+// is recorded in `errors` and skipped, so lexing always reaches the end.
 pub struct Lexer {
     input: String,
+    file: usize,
     start: usize,
     current: usize,
-    line: usize
+    line: usize,
+    column: usize,
+    start_line: usize,
+    start_column: usize,
+    errors: Vec<LexError>,
 }
 
 impl Lexer {
 
-    pub fn new(input: String) -> Lexer {
-        Lexer { input, start: 0, current: 0 , line: 1}
+    pub fn new(input: String, file: usize) -> Lexer {
+        Lexer {
+            input,
+            file,
+            start: 0,
+            current: 0,
+            line: 1,
+            column: 1,
+            start_line: 1,
+            start_column: 1,
+            errors: Vec::new(),
+        }
     }
 
     pub fn next_token(&mut self) -> Token {
+        loop {
+            if let Some(token) = self.scan_token() {
+                return token;
+            }
+        }
+    }
+
+    pub fn errors(&self) -> &Vec<LexError> {
+        &self.errors
+    }
+
+    // Whether an unterminated string or block comment swallowed the rest of
+    // the input, in which case anything missing at the end is already
+    // explained.
+    pub fn unterminated(&self) -> bool {
+        return self.errors.iter().any(|error| matches!(error.code, Code::UnterminatedString | Code::UnterminatedComment));
+    }
 
-        if self.peek_next() == '\0' { return Token::new(TokenType::Eof, String::new()); }
+    // Scans one lexeme, returning None if nothing usable came of it.
+    fn scan_token(&mut self) -> Option<Token> {
 
         self.skip_whitespace();
 
         self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column;
+
+        if self.is_at_end() { return Some(self.make_token(TokenType::Eof)); }
 
         let ch = self.advance();
 
         match ch {
-            '(' => { return Token::new(TokenType::LeftParen, "(".to_owned()); }
-            ')' => { return Token::new(TokenType::RightParen, ")".to_owned()); }
-            '{' => { return Token::new(TokenType::LeftBrace, "{".to_owned()); }
-            '}' => { return Token::new(TokenType::RightBrace, "}".to_owned()); }
-            ';' => { return Token::new(TokenType::Semicolon, ";".to_owned()); }
-            ',' => { return Token::new(TokenType::Comma, ",".to_owned()); }
-            '.' => { return Token::new(TokenType::Dot, ".".to_owned()); }
-            '-' => { return Token::new(TokenType::Minus, "-".to_owned()); }
-            '+' => { return Token::new(TokenType::Plus, "+".to_owned()); }
-            '*' => { return Token::new(TokenType::Star, "*".to_owned()); }
+            '(' => { return Some(self.make_token(TokenType::LeftParen)); }
+            ')' => { return Some(self.make_token(TokenType::RightParen)); }
+            '{' => { return Some(self.make_token(TokenType::LeftBrace)); }
+            '}' => { return Some(self.make_token(TokenType::RightBrace)); }
+            ';' => { return Some(self.make_token(TokenType::Semicolon)); }
+            ',' => { return Some(self.make_token(TokenType::Comma)); }
+            '.' => {
+                if self.peek().is_ascii_digit() {
+                    self.decimal_digits();
+                    let help = format!("Write it as '0{}'", self.lexeme());
+                    self.errors.push(LexError::new(Code::MalformedNumber, self.span(), format!("Number '{}' needs a digit before the '.'", self.lexeme()))
+                        .with_help(&help));
+                    return Some(self.make_token(TokenType::NumberLiteral));
+                }
+                return Some(self.make_token(TokenType::Dot));
+            }
+            '-' => { return Some(self.make_token(TokenType::Minus)); }
+            '+' => { return Some(self.make_token(TokenType::Plus)); }
+            '*' => { return Some(self.make_token(TokenType::Star)); }
             '=' => {
                 if self.peek() == '=' {
                     self.advance();
-                    return Token::new(TokenType::EqualEqual, "==".to_owned());
+                    return Some(self.make_token(TokenType::EqualEqual));
                 } else {
-                    return Token::new(TokenType::Equal, "=".to_owned());
+                    return Some(self.make_token(TokenType::Equal));
                 }
             }
             '!' => {
                 if self.peek() == '=' {
                     self.advance();
-                    return Token::new(TokenType::BangEqual, "!=".to_owned());
+                    return Some(self.make_token(TokenType::BangEqual));
                 } else {
-                    return Token::new(TokenType::Bang, "!".to_owned());
+                    return Some(self.make_token(TokenType::Bang));
                 }
             }
             '<' => {
                 if self.peek() == '=' {
                     self.advance();
-                    return Token::new(TokenType::LessEqual, "<=".to_owned());
+                    return Some(self.make_token(TokenType::LessEqual));
                 } else {
-                    return Token::new(TokenType::Less, "<".to_owned());
+                    return Some(self.make_token(TokenType::Less));
                 }
             }
             '>' => {
                 if self.peek() == '=' {
                     self.advance();
-                    return Token::new(TokenType::GreaterEqual, ">=".to_owned());
+                    return Some(self.make_token(TokenType::GreaterEqual));
                 } else {
-                    return Token::new(TokenType::Greater, ">".to_owned());
+                    return Some(self.make_token(TokenType::Greater));
                 }
             }
             '"' => {
-                return self.string();
+                return Some(self.string());
             }
             '/' => {
-                if self.peek() == '/' {
-                    self.advance();
-                    self.skip_comment();
-                    return self.next_token();
-                } else {
-                    return Token::new(TokenType::Slash, "/".to_owned());
+                if self.at_doc_comment(self.start) {
+                    while !self.is_at_end() && self.peek() != '\n' {
+                        self.advance();
+                    }
+                    return Some(self.make_token(TokenType::DocComment));
                 }
+                return Some(self.make_token(TokenType::Slash));
+            }
+            '0'..='9' => { return Some(self.number()); }
+            c if c.is_alphabetic() => { return Some(self.identifier()); }
+            _ => {
+                self.error(Code::UnexpectedCharacter, format!("Unexpected character '{}'", ch.escape_debug()));
+                return None;
             }
-            'A'..='Z' | 'a'..='z' => { return self.identifier(); }
-            '0'..='9' => { return self.number(); }
-            _  => Token::new(TokenType::Unknown, String::new())
         }
 
     }
@@ -89,129 +195,325 @@ impl Lexer {
         let mut ret : Vec<Token> = Vec::new();
         loop {
             let token = self.next_token();
-            if token.token_type == TokenType::Eof {
+            let is_eof = token.token_type == TokenType::Eof;
+            ret.push(token);
+            if is_eof {
                 break;
             }
-            ret.push(token);
         }
-        ret.push(Token::new(TokenType::Eof, String::new()));
         return ret;
     }
 
+    fn lexeme(&self) -> &str {
+        &self.input[self.start..self.current]
+    }
+
+    fn span(&self) -> Span {
+        return Span::new(self.file, self.start, self.current, self.start_line, self.start_column);
+    }
+
+    fn make_token(&self, token_type: TokenType) -> Token {
+        return Token::new(token_type, self.lexeme().to_owned(), self.span());
+    }
+
+    fn error(&mut self, code: Code, message: String) {
+        self.errors.push(LexError::new(code, self.span(), message));
+    }
+
+    // Skips whitespace and comments, stopping at `///` doc comments, which
+    // become tokens of their own.
     fn skip_whitespace(&mut self) {
         loop {
             let ch = self.peek();
             match ch {
-                ' ' | '\r' | '\t' => { self.advance(); }
-                '\n' => {
-                    self.line += 1;
-                    self.advance();
+                ' ' | '\r' | '\t' | '\n' => { self.advance(); }
+                '/' if self.peek_next() == '/' => {
+                    if self.at_doc_comment(self.current) {
+                        return;
+                    }
+                    self.skip_comment();
                 }
+                '/' if self.peek_next() == '*' => { self.skip_block_comment(); }
                 _ => { return }
             }
         }
     }
 
+    // `////` and longer are ordinary comments, often used as separators. So
+    // is a `///` after code on the same line, which documents nothing.
+    fn at_doc_comment(&self, offset: usize) -> bool {
+        let rest = &self.input[offset..];
+        if !rest.starts_with("///") || rest.starts_with("////") {
+            return false;
+        }
+        let line = self.input[..offset].rsplit('\n').next().unwrap_or("");
+        return line.chars().all(|ch| ch == ' ' || ch == '\t' || ch == '\r');
+    }
+
     fn skip_comment(&mut self) {
+        while !self.is_at_end() {
+            if self.advance() == '\n' {
+                return;
+            }
+        }
+    }
+
+    // Block comments nest, so `/* a /* b */ c */` is a single comment.
+    fn skip_block_comment(&mut self) {
+        let start = self.current;
+        let (line, column) = (self.line, self.column);
+        let mut depth = 0;
         loop {
-            let ch = self.peek();
-            match ch {
-                '\0' => { break; }
-                '\n' => {
-                    self.line += 1;
+            if self.is_at_end() {
+                let span = Span::new(self.file, start, self.current, line, column);
+                self.errors.push(LexError::new(Code::UnterminatedComment, span, "Unterminated block comment".to_owned())
+                    .with_help("Block comments nest, so every '/*' needs its own '*/'"));
+                return;
+            }
+            match (self.peek(), self.peek_next()) {
+                ('/', '*') => {
+                    self.advance();
                     self.advance();
-                    return;
+                    depth += 1;
+                }
+                ('*', '/') => {
+                    self.advance();
+                    self.advance();
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
                 }
                 _ => { self.advance(); }
             }
         }
     }
 
+    // Input may contain NUL itself, so the end is found by position rather
+    // than by the '\0' that `peek` returns there.
+    fn is_at_end(&self) -> bool {
+        return self.current >= self.input.len();
+    }
+
     fn peek_next(&self) -> char {
-        let peek_index = self.current + 1;
-        if peek_index >= self.input.len() {
-            '\0'
+        let mut chars = self.input[self.current..].chars();
+        chars.next();
+        chars.next().unwrap_or('\0')
+    }
+
+    fn peek(&self) -> char {
+        self.input[self.current..].chars().next().unwrap_or('\0')
+    }
+
+    fn advance(&mut self) -> char {
+        let ch = self.peek();
+        self.current += ch.len_utf8();
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
         } else {
-            self.input.chars().nth(peek_index).unwrap()
+            self.column += 1;
         }
+        ch
     }
 
-    fn peek(&self) -> char {
-        if self.current >= self.input.len() {
-            '\0'
+    fn identifier(&mut self) -> Token {
+        while self.peek().is_alphanumeric() {
+            self.advance();
+        }
+
+        let token_type = KEYWORDS.iter()
+            .find(|(keyword, _)| *keyword == self.lexeme())
+            .map_or(TokenType::Identifier, |(_, token_type)| token_type.clone());
+
+        return self.make_token(token_type);
+    }
+
+    // Number literals are decimal (`12`, `1.5`, `1e-9`) or, after a `0x`,
+    // `0b` or `0o` prefix, hexadecimal, binary or octal integers. Any of them
+    // may use `_` between digits as a separator.
+    fn number(&mut self) -> Token {
+        let radix = if self.lexeme() == "0" {
+            match self.peek() {
+                'x' => 16,
+                'b' => 2,
+                'o' => 8,
+                _ => 10,
+            }
         } else {
-            self.input.chars().nth(self.current).unwrap()
+            10
+        };
+        if radix != 10 {
+            return self.radix_number(radix);
+        }
+
+        self.decimal_digits();
+        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
+            self.advance();
+            self.decimal_digits();
+        }
+        if self.peek() == 'e' || self.peek() == 'E' {
+            self.advance();
+            if self.peek() == '+' || self.peek() == '-' {
+                self.advance();
+            }
+            if !self.peek().is_ascii_digit() {
+                return self.malformed_number();
+            }
+            self.decimal_digits();
+        }
+
+        // Letters running straight into a number, as in `12abc`.
+        if self.peek().is_alphanumeric() || self.peek() == '_' {
+            return self.malformed_number();
+        }
+        if !Lexer::separators_valid(self.lexeme(), 10) {
+            return self.malformed_number();
+        }
+
+        let text = self.lexeme().replace('_', "");
+        match text.parse::<f64>() {
+            Ok(value) => return Token::number(self.lexeme().to_owned(), value, self.span()),
+            Err(_) => return self.malformed_number(),
         }
     }
 
-    fn advance(&mut self) -> char {
-        self.current += 1;
-        self.input.chars().nth(self.current - 1).unwrap()
+    fn radix_number(&mut self, radix: u32) -> Token {
+        self.advance();
+        while self.peek().is_alphanumeric() || self.peek() == '_' {
+            self.advance();
+        }
+
+        let digits = &self.lexeme()[2..];
+        if digits.is_empty() || !Lexer::separators_valid(digits, radix) {
+            return self.malformed_number();
+        }
+        let mut value = 0.0;
+        for ch in digits.chars().filter(|ch| *ch != '_') {
+            match ch.to_digit(radix) {
+                Some(digit) => value = value * radix as f64 + digit as f64,
+                None => return self.malformed_number(),
+            }
+        }
+        return Token::number(self.lexeme().to_owned(), value, self.span());
     }
 
-    fn identifier(&mut self) -> Token {
-        while self.peek().is_alphabetic() || self.peek().is_digit(10) { 
-            self.advance(); 
-        }
-
-        let iden: String = self.input.chars()
-                            .skip(self.start)
-                            .take(self.current - self.start)
-                            .collect();
-
-        match iden.as_str() {
-            "and" => return Token::new(TokenType::And, "and".to_owned()),
-            "class" => return Token::new(TokenType::Class, "class".to_owned()),
-            "else" => return Token::new(TokenType::Else, "else".to_owned()),
-            "false" => return Token::new(TokenType::False, "false".to_owned()),
-            "fun" => return Token::new(TokenType::Fun, "fun".to_owned()),
-            "for" => return Token::new(TokenType::For, "for".to_owned()),
-            "if" => return Token::new(TokenType::If, "if".to_owned()),
-            "nil" => return Token::new(TokenType::Nil, "nil".to_owned()),
-            "or" => return Token::new(TokenType::Or, "or".to_owned()),
-            "print" => return Token::new(TokenType::Print, "print".to_owned()),
-            "return" => return Token::new(TokenType::Return, "return".to_owned()),
-            "super" => return Token::new(TokenType::Super, "super".to_owned()),
-            "this" => return Token::new(TokenType::This, "this".to_owned()),
-            "true" => return Token::new(TokenType::True, "true".to_owned()),
-            "var" => return Token::new(TokenType::Var, "var".to_owned()),
-            "while" => return Token::new(TokenType::While, "while".to_owned()),
-            _ => {}
-        }
-
-        return Token::new(TokenType::Identifier, iden);
-    }
-
-    fn number(&mut self) -> Token { 
-        while self.peek().is_digit(10) { self.advance(); }
-
-        if self.peek() == '.' && self.peek_next().is_digit(10) {
+    fn decimal_digits(&mut self) {
+        while self.peek().is_ascii_digit() || self.peek() == '_' {
             self.advance();
-            while self.peek().is_digit(10) { self.advance(); }
         }
+    }
 
-        return Token::new(TokenType::NumberLiteral, 
-                        self.input.chars()
-                            .skip(self.start)
-                            .take(self.current - self.start)
-                            .collect());
+    // Separators may only sit between two digits of the given radix, so not
+    // next to a '.', an exponent or another separator.
+    fn separators_valid(text: &str, radix: u32) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        for (i, ch) in chars.iter().enumerate() {
+            if *ch != '_' {
+                continue;
+            }
+            let before = i > 0 && chars[i - 1].is_digit(radix);
+            let after = i + 1 < chars.len() && chars[i + 1].is_digit(radix);
+            if !before || !after {
+                return false;
+            }
+        }
+        return true;
     }
 
+    // Skips the rest of a bad number so it is reported as a single error.
+    // The token is still produced, without a value, so the parser doesn't
+    // pile more errors on top.
+    fn malformed_number(&mut self) -> Token {
+        while self.peek().is_alphanumeric() || self.peek() == '_' {
+            self.advance();
+        }
+        self.error(Code::MalformedNumber, format!("Malformed number '{}'", self.lexeme()));
+        return self.make_token(TokenType::NumberLiteral);
+    }
+
+    // Decodes escapes as it goes into the token's string value, while its
+    // literal keeps the source text, quotes included. A bad escape is
+    // reported and dropped, and an unterminated string runs to the end of the
+    // input. Either way the string is still produced so the parser doesn't
+    // trip over its absence.
     fn string(&mut self) -> Token {
-        while self.peek() != '"' && self.peek() != '\0' {
-            if self.peek() == '\n' {
-                self.line += 1;
+        let mut value = String::new();
+        while !self.is_at_end() && self.peek() != '"' {
+            if self.peek() == '\\' {
+                if let Some(ch) = self.escape() {
+                    value.push(ch);
+                }
+            } else {
+                value.push(self.advance());
+            }
+        }
+        if self.is_at_end() {
+            self.error(Code::UnterminatedString, "Unterminated string".to_owned());
+        } else {
+            self.advance();
+        }
+        return Token::string(self.lexeme().to_owned(), value, self.span());
+    }
+
+    fn escape(&mut self) -> Option<char> {
+        let start = self.current;
+        let (line, column) = (self.line, self.column);
+        self.advance();
+
+        let decoded = match self.peek() {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            'u' => {
+                self.advance();
+                let decoded = self.unicode_escape();
+                if decoded.is_none() {
+                    let span = Span::new(self.file, start, self.current, line, column);
+                    let message = format!("Invalid unicode escape '{}'", &self.input[start..self.current]);
+                    self.errors.push(LexError::new(Code::InvalidEscape, span, message)
+                        .with_help("Write a character as '\\u{...}' with one to six hex digits"));
+                }
+                return decoded;
             }
+            // Left for `string` to report as unterminated.
+            _ if self.is_at_end() => return None,
+            _ => None,
+        };
+        self.advance();
+
+        if decoded.is_none() {
+            let span = Span::new(self.file, start, self.current, line, column);
+            let message = format!("Invalid escape sequence '{}'", &self.input[start..self.current]);
+            self.errors.push(LexError::new(Code::InvalidEscape, span, message)
+                .with_help("Valid escapes are '\\n', '\\t', '\\\\', '\\\"' and '\\u{...}'"));
+        }
+        return decoded;
+    }
+
+    // Reads the `{XXXX}` part of a `\u{XXXX}` escape: one to six hex digits
+    // naming a Unicode scalar value.
+    fn unicode_escape(&mut self) -> Option<char> {
+        if self.peek() != '{' {
+            return None;
+        }
+        self.advance();
+
+        let digits_start = self.current;
+        while self.peek().is_ascii_hexdigit() {
             self.advance();
         }
-        if self.peek() == '\0' {
-            return Token::new(TokenType::Unknown, "Unterimated string".to_owned());
+        let digits_end = self.current;
+        if self.peek() != '}' {
+            return None;
         }
         self.advance();
-        return Token::new(TokenType::StringLiteral, 
-                          self.input.chars()
-                            .skip(self.start)
-                            .take(self.current - self.start)
-                            .collect());
+
+        let digits = &self.input[digits_start..digits_end];
+        if digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+        return u32::from_str_radix(digits, 16).ok().and_then(char::from_u32);
     }
 }
@@ -0,0 +1,18 @@
+pub mod lexer;
+pub mod token;
+pub mod expr;
+pub mod parser;
+pub mod interpreter;
+pub mod value;
+pub mod environment;
+pub mod function;
+pub mod class;
+pub mod resolver;
+pub mod diagnostic;
+pub mod printer;
+
+// Stack size for the thread that runs a program. Every pass walks the tree
+// recursively, and the parser and interpreter only stop a program once it
+// nests `MAX_NESTING_DEPTH` or `MAX_DEPTH` deep, which an unoptimized
+// build can't fit in the default 8 MiB.
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
@@ -1,56 +1,356 @@
+mod repl;
+
+use std::env;
+use std::fs;
 use std::io;
-use std::env::args;
-use std::fs::File;
 use std::io::prelude::*;
+use std::process;
+use std::rc::Rc;
+use std::thread;
+
+use proto_rust::expr::Stmt;
+use proto_rust::lexer::Lexer;
+use proto_rust::parser::{Parser, ParseError};
+use proto_rust::token::TokenType;
+use proto_rust::interpreter::Interpreter;
+use proto_rust::resolver::Resolver;
+use proto_rust::function::NativeFunction;
+use proto_rust::value::Value;
+use proto_rust::printer::AstPrinter;
+use proto_rust::diagnostic::{self, Diagnostic, Emitter, JsonEmitter, SourceMap};
+use proto_rust::STACK_SIZE;
+
+const USAGE: &str = "\
+Usage: proto [command] [options] [input] [args...]
+
+Commands:
+  run <input> [args...]  Run a script (the default when given an input)
+  repl                   Start an interactive session (the default with no arguments)
+  check <input>          Parse and resolve a script without running it
+  tokens <input>         Print the tokens of a script
+  ast [--tree] <input>   Print the syntax tree of a script as S-expressions, or
+                         as an indented tree
 
-mod lexer;
-mod token;
-mod expr;
-mod parser;
-mod interpreter;
+Input:
+  <file>                 Read the script from a file
+  -                      Read the script from standard input
+  -e <code>              Use <code> as the script
+
+Options:
+  --error-format=<fmt>   Report errors as 'human' (the default) or 'json', one
+                         object per line
+  -h, --help             Show this message";
+
+// Exit codes, following the BSD sysexits convention.
+const EX_USAGE: i32 = 64;
+const EX_DATAERR: i32 = 65;
+const EX_NOINPUT: i32 = 66;
+const EX_SOFTWARE: i32 = 70;
+
+// Why a program didn't run to completion.
+pub enum Failure {
+    // Lexing, parsing or resolving reported errors, so nothing ran.
+    Compile,
+    Runtime,
+}
+
+impl Failure {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Failure::Compile => EX_DATAERR,
+            Failure::Runtime => EX_SOFTWARE,
+        }
+    }
+}
+
+// How diagnostics are written to stderr.
+#[derive(Clone,Copy,PartialEq)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+pub fn emit(sources: &SourceMap, format: ErrorFormat, diagnostic: &Diagnostic) {
+    match format {
+        ErrorFormat::Human => Emitter::new(sources, diagnostic::stderr_color()).emit(diagnostic),
+        ErrorFormat::Json => JsonEmitter::new(sources).emit(diagnostic),
+    }
+}
 
-use crate::lexer::Lexer;
-use crate::parser::Parser;
-use crate::interpreter::Interpreter;
+// Whether a parse error only follows from an unterminated string or comment
+// running to the end of the input, and so is left unreported.
+fn cascades(lexer: &Lexer, error: &ParseError) -> bool {
+    return lexer.unterminated() && error.found.token_type == TokenType::Eof;
+}
 
-fn eval(code: &str) {
-    let mut lexer = Lexer::new(code.to_owned());
+// Lexes, parses and resolves a file, reporting every error found.
+pub fn compile(interpreter: &mut Interpreter, sources: &SourceMap, format: ErrorFormat, file: usize) -> Result<Vec<Box<dyn Stmt>>, Failure> {
+    let mut lexer = Lexer::new(sources.get(file).source.clone(), file);
 
     let tokens = lexer.tokens();
+    for error in lexer.errors() {
+        emit(sources, format, &error.diagnostic());
+    }
     let mut parser = Parser::new(tokens);
-    let statements = parser.parse();
+    let statements = match parser.parse() {
+        Ok(statements) => statements,
+        Err(errors) => {
+            for error in errors {
+                if !cascades(&lexer, &error) {
+                    emit(sources, format, &error.diagnostic());
+                }
+            }
+            return Err(Failure::Compile);
+        }
+    };
 
-    let mut interpreter = Interpreter::new();
-    interpreter.interpret(&statements);
+    // The tree is still whole after lex errors, so it's worth resolving to
+    // report everything wrong with the program at once.
+    let mut resolver = Resolver::new(interpreter);
+    if let Err(errors) = resolver.resolve(&statements) {
+        for error in errors {
+            emit(sources, format, &error.diagnostic());
+        }
+        return Err(Failure::Compile);
+    }
+    if !lexer.errors().is_empty() {
+        return Err(Failure::Compile);
+    }
+    return Ok(statements);
+}
+
+// Runs a file as a complete program.
+pub fn eval(interpreter: &mut Interpreter, sources: &SourceMap, format: ErrorFormat, file: usize) -> Result<(), Failure> {
+    let statements = compile(interpreter, sources, format, file)?;
+    if let Err(error) = interpreter.interpret(&statements) {
+        emit(sources, format, &error.diagnostic());
+        return Err(Failure::Runtime);
+    }
+    return Ok(());
+}
+
+pub fn print_tokens(sources: &SourceMap, format: ErrorFormat, file: usize) -> Result<(), Failure> {
+    let mut lexer = Lexer::new(sources.get(file).source.clone(), file);
+    for token in lexer.tokens() {
+        println!("{:<8} {:?} {}", token.span.to_string(), token.token_type, token.literal);
+    }
+    for error in lexer.errors() {
+        emit(sources, format, &error.diagnostic());
+    }
+    if !lexer.errors().is_empty() {
+        return Err(Failure::Compile);
+    }
+    return Ok(());
+}
+
+// Prints the tree for a file, which may hold a program or a bare expression,
+// one top-level node per line unless `tree` asks for the indented layout.
+pub fn print_ast(sources: &SourceMap, format: ErrorFormat, file: usize, tree: bool) -> Result<(), Failure> {
+    let mut printer = if tree { AstPrinter::tree() } else { AstPrinter::sexpr() };
+    let mut lexer = Lexer::new(sources.get(file).source.clone(), file);
+    let tokens = lexer.tokens();
+    for error in lexer.errors() {
+        emit(sources, format, &error.diagnostic());
+    }
+    if let Ok(expr) = Parser::new(tokens.clone()).parse_expression() {
+        println!("{}", printer.print_expr(&expr));
+    } else {
+        match Parser::new(tokens).parse() {
+            Ok(statements) => {
+                for statement in statements {
+                    println!("{}", printer.print_stmt(&statement));
+                }
+            }
+            Err(errors) => {
+                for error in errors {
+                    if !cascades(&lexer, &error) {
+                        emit(sources, format, &error.diagnostic());
+                    }
+                }
+                return Err(Failure::Compile);
+            }
+        }
+    }
+    if !lexer.errors().is_empty() {
+        return Err(Failure::Compile);
+    }
+    return Ok(());
 }
 
-fn repl() {
+// Makes the script's arguments available as `argc` and `arg(i)`, which
+// returns nil for an index out of range.
+fn define_arguments(interpreter: &mut Interpreter, arguments: &[String]) {
+    interpreter.define_global("argc", Value::Number(arguments.len() as f64));
+    let arguments = arguments.to_vec();
+    let arg = NativeFunction::new("arg", 1, Box::new(move |values| {
+        match values[0] {
+            Value::Number(index) if index >= 0.0 && index.fract() == 0.0 => {
+                return arguments.get(index as usize).map_or(Value::Nil, |argument| Value::Str(argument.clone()));
+            }
+            _ => return Value::Nil,
+        }
+    }));
+    interpreter.define_global("arg", Value::Native(Rc::new(arg)));
+}
 
-    loop {
-        let mut code = String::new();
-        print!(">> ");
-        io::stdout().flush().unwrap();
-        io::stdin().read_line(&mut code).unwrap();
-        eval(&code);
-        println!()
+pub enum Input {
+    File(String),
+    Stdin,
+    Inline(String),
+}
+
+pub fn read(input: &Input) -> Result<String, String> {
+    match input {
+        Input::File(path) => return fs::read_to_string(path).map_err(|error| format!("Couldn't read '{}': {}", path, describe(&error))),
+        Input::Stdin => {
+            let mut code = String::new();
+            match io::stdin().read_to_string(&mut code) {
+                Ok(_) => return Ok(code),
+                Err(error) => return Err(format!("Couldn't read standard input: {}", describe(&error))),
+            }
+        }
+        Input::Inline(code) => return Ok(code.clone()),
     }
 }
 
-fn read(path: &str) {
+fn describe(error: &io::Error) -> String {
+    match error.kind() {
+        io::ErrorKind::NotFound => return "no such file".to_owned(),
+        io::ErrorKind::PermissionDenied => return "permission denied".to_owned(),
+        io::ErrorKind::IsADirectory => return "it is a directory".to_owned(),
+        io::ErrorKind::InvalidData => return "it is not valid UTF-8".to_owned(),
+        _ => return error.to_string(),
+    }
+}
 
-    let mut code = String::new();
+// Splits `arguments` into the input and whatever follows it.
+fn parse_input(arguments: &[String]) -> Result<(Input, &[String]), String> {
+    match arguments.first().map(String::as_str) {
+        None => return Err("Missing input file".to_owned()),
+        Some("-e") => match arguments.get(1) {
+            Some(code) => return Ok((Input::Inline(code.clone()), &arguments[2..])),
+            None => return Err("Expected code after '-e'".to_owned()),
+        },
+        Some("-") => return Ok((Input::Stdin, &arguments[1..])),
+        Some(option) if option.starts_with('-') => return Err(format!("Unknown option '{}'", option)),
+        Some(path) => return Ok((Input::File(path.to_owned()), &arguments[1..])),
+    }
+}
 
-    let mut file = File::open(path).expect("file not found");
-    file.read_to_string(&mut code).expect("something went wrong reading the file");
-        
-    eval(&code);
+fn usage_error(message: &str) -> i32 {
+    eprintln!("proto: {}", message);
+    eprintln!("Run 'proto --help' for usage");
+    return EX_USAGE;
 }
 
-fn main() {
+// Removes any `--error-format` options from the front of `arguments`. The
+// last one given wins over `format`.
+fn parse_error_format(mut format: ErrorFormat, mut arguments: &[String]) -> Result<(ErrorFormat, &[String]), String> {
+    while let Some(option) = arguments.first().and_then(|argument| argument.strip_prefix("--error-format")) {
+        let value = match option.strip_prefix('=') {
+            Some(value) => value,
+            None if option.is_empty() => match arguments.get(1) {
+                Some(value) => {
+                    arguments = &arguments[1..];
+                    value.as_str()
+                }
+                None => return Err("Expected a format after '--error-format'".to_owned()),
+            },
+            None => break,
+        };
+        format = match value {
+            "human" => ErrorFormat::Human,
+            "json" => ErrorFormat::Json,
+            _ => return Err(format!("Unknown error format '{}', expected 'human' or 'json'", value)),
+        };
+        arguments = &arguments[1..];
+    }
+    return Ok((format, arguments));
+}
 
-    if args().len() == 1 {
-        repl();
-    } else {
-        read(&args().nth(1).unwrap());
+fn run(arguments: &[String]) -> i32 {
+    // Options may come before the command as well as after it.
+    let (format, arguments) = match parse_error_format(ErrorFormat::Human, arguments) {
+        Ok(parsed) => parsed,
+        Err(message) => return usage_error(&message),
+    };
+    let (command, rest) = match arguments.first().map(String::as_str) {
+        None => return repl(),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        Some(command @ ("run" | "repl" | "check" | "tokens" | "ast")) => (command, &arguments[1..]),
+        // A bare input is run.
+        Some(_) => ("run", arguments),
+    };
+    let (format, mut rest) = match parse_error_format(format, rest) {
+        Ok(parsed) => parsed,
+        Err(message) => return usage_error(&message),
+    };
+    let tree = command == "ast" && rest.first().is_some_and(|argument| argument == "--tree");
+    if tree {
+        rest = &rest[1..];
+    }
+
+    if command == "repl" {
+        if let Some(argument) = rest.first() {
+            return usage_error(&format!("Unexpected argument '{}'", argument));
+        }
+        return repl();
     }
+
+    let (input, script_arguments) = match parse_input(rest) {
+        Ok(parsed) => parsed,
+        Err(message) => return usage_error(&message),
+    };
+    if command != "run" {
+        if let Some(argument) = script_arguments.first() {
+            return usage_error(&format!("Unexpected argument '{}'", argument));
+        }
+    }
+    let code = match read(&input) {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("proto: {}", message);
+            return EX_NOINPUT;
+        }
+    };
+
+    let name = match &input {
+        Input::File(path) => path.as_str(),
+        Input::Stdin => "<stdin>",
+        Input::Inline(_) => "<inline>",
+    };
+    let mut sources = SourceMap::new();
+    let file = sources.add(name, code);
+
+    let mut interpreter = Interpreter::new();
+    let result = match command {
+        "run" => {
+            define_arguments(&mut interpreter, script_arguments);
+            eval(&mut interpreter, &sources, format, file)
+        }
+        "check" => compile(&mut interpreter, &sources, format, file).map(|_| ()),
+        "tokens" => print_tokens(&sources, format, file),
+        _ => print_ast(&sources, format, file, tree),
+    };
+    match result {
+        Ok(()) => return 0,
+        Err(failure) => return failure.exit_code(),
+    }
+}
+
+fn repl() -> i32 {
+    repl::repl();
+    return 0;
+}
+
+fn main() {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let runner = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(&arguments))
+        .expect("failed to spawn interpreter thread");
+    process::exit(runner.join().unwrap_or(EX_SOFTWARE));
 }
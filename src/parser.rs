@@ -1,135 +1,519 @@
-use crate::token::{Token, TokenType};
-use crate::expr::{Expr, LiteralExpr, UnaryExpr, BinaryExpr};
-use crate::expr::{Stmt, ExpressionStmt, PrintStmt};
+use crate::token::{Token, TokenType, Span};
+use crate::diagnostic::{Code, Diagnostic, Label};
+use std::collections::HashMap;
+use std::rc::Rc;
 
-// program      ->  statement* EOF
-// statement    ->  expr_stmt | print_stmt
+use crate::expr::{Expr, LiteralExpr, UnaryExpr, BinaryExpr, VariableExpr, AssignExpr, LogicalExpr, CallExpr};
+use crate::expr::{GetExpr, SetExpr, ThisExpr, SuperExpr};
+use crate::expr::{Stmt, ExpressionStmt, PrintStmt, VarStmt, BlockStmt, IfStmt, WhileStmt, ForStmt};
+use crate::expr::{FunctionStmt, ReturnStmt, ClassStmt};
+
+// program      ->  declaration* EOF
+// declaration  ->  class_decl | fun_decl | var_decl | statement
+// class_decl   ->  "class" IDENTIFIER ("<" IDENTIFIER)? "{" function* "}"
+// fun_decl     ->  "fun" function
+// function     ->  IDENTIFIER "(" parameters? ")" block
+// parameters   ->  IDENTIFIER ("," IDENTIFIER)*
+// var_decl     ->  "var" IDENTIFIER ("=" expr)? ";"
+// statement    ->  expr_stmt | print_stmt | if_stmt | while_stmt | for_stmt |
+//                  return_stmt | block
 // expr_stmt     ->  expr ";"
 // print_stmt    ->  "print" expr ";"
-// expr         ->  equality
+// if_stmt      ->  "if" "(" expr ")" statement ("else" statement)?
+// while_stmt   ->  "while" "(" expr ")" statement
+// for_stmt     ->  "for" "(" (var_decl | expr_stmt | ";") expr? ";" expr? ")" statement
+// return_stmt  ->  "return" expr? ";"
+// block        ->  "{" declaration* "}"
+// expr         ->  assignment
+// assignment   ->  (call ".")? IDENTIFIER "=" assignment | logic_or
+// logic_or     ->  logic_and ("or" logic_and)*
+// logic_and    ->  equality ("and" equality)*
 // equality     ->  comparison (("!=" | "==") comparison)*
 // comparison   ->  term ((">" | ">=" | "<" | "<=") term)*
 // term         ->  factor (('-' | '+') factor)*
 // factor       ->  unary (('*' | '/') unary)*
-// unary        ->  ('+' | '-') unary | primary
-// primary      ->  num | '(' expr ')'
+// unary        ->  ('+' | '-' | '!') unary | call
+// call         ->  primary ("(" arguments? ")" | "." IDENTIFIER)*
+// arguments    ->  expr ("," expr)*
+// primary      ->  num | string | "true" | "false" | "nil" | "this" | IDENTIFIER |
+//                  '(' expr ')' | "super" "." IDENTIFIER
+
+// Deeper nesting of expressions or statements than this is reported as an
+// error instead of overflowing the native stack. Each level costs a dozen
+// frames here and more in later passes, which is more than a default thread
+// has room for, so programs are run on a thread of `STACK_SIZE`.
+const MAX_NESTING_DEPTH: usize = 1024;
+
+#[derive(Debug,Clone)]
+pub struct ParseError {
+    pub code: Code,
+    pub found: Token,
+    pub message: String,
+    pub labels: Vec<Label>,
+}
+
+impl ParseError {
+    pub fn new(code: Code, found: Token, message: String) -> ParseError {
+        ParseError {
+            code,
+            found,
+            message,
+            labels: Vec::new(),
+        }
+    }
+
+    pub fn with_label(mut self, span: Span, message: String) -> ParseError {
+        self.labels.push(Label::new(span, message));
+        return self;
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        let message = if self.found.token_type == TokenType::Eof {
+            format!("{}, found end of input", self.message)
+        } else {
+            format!("{}, found '{}'", self.message, self.found.literal)
+        };
+        return Diagnostic::error(self.code, self.found.span, message).with_labels(&self.labels);
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.found.token_type == TokenType::Eof {
+            write!(f, "[{}] Error at end: {}", self.found.span, self.message)
+        } else {
+            write!(f, "[{}] Error at '{}': {}", self.found.span, self.found.literal, self.message)
+        }
+    }
+}
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    depth: usize,
+    // Text of the `///` lines before a token, keyed by the token's index.
+    docs: HashMap<usize, String>,
 }
 
 impl Parser {
+    // Doc comments are taken out of the token stream here, so the grammar
+    // never sees them.
     pub fn new(tokens: Vec<Token>) -> Parser {
+        let mut kept = Vec::with_capacity(tokens.len());
+        let mut docs = HashMap::new();
+        let mut lines: Vec<String> = Vec::new();
+        for token in tokens {
+            if token.token_type == TokenType::DocComment {
+                let text = token.literal[3..].trim_end_matches('\r');
+                lines.push(text.strip_prefix(' ').unwrap_or(text).to_owned());
+                continue;
+            }
+            if !lines.is_empty() {
+                docs.insert(kept.len(), lines.join("\n"));
+                lines.clear();
+            }
+            kept.push(token);
+        }
         Parser {
-            tokens,
+            tokens: kept,
             current: 0,
+            depth: 0,
+            docs,
         }
     }
 
-    pub fn parse(&mut self) -> Vec<Box<dyn Stmt>> {
+    pub fn parse(&mut self) -> Result<Vec<Box<dyn Stmt>>, Vec<ParseError>> {
         let mut statements = Vec::new();
+        let mut errors = Vec::new();
+        while !self.is_at_end() {
+            match self.declaration() {
+                Ok(statement) => statements.push(statement),
+                Err(error) => {
+                    errors.push(*error);
+                    self.depth = 0;
+                    self.synchronize();
+                }
+            }
+        }
+        if errors.is_empty() {
+            return Ok(statements);
+        }
+        return Err(errors);
+    }
+
+    // Parses the whole input as a single expression with no trailing `;`.
+    pub fn parse_expression(&mut self) -> Result<Box<dyn Expr>, Box<ParseError>> {
+        let expr = self.expr()?;
+        if !self.is_at_end() {
+            return Err(self.error(Code::ExpectedToken, "Expected end of expression"));
+        }
+        return Ok(expr);
+    }
+
+    // Discards tokens until the start of the next statement so that parsing
+    // can resume after an error.
+    fn synchronize(&mut self) {
         while !self.is_at_end() {
-            statements.push(self.statement());
+            if self.pull().token_type == TokenType::Semicolon {
+                return;
+            }
+            match self.peek().token_type {
+                TokenType::Class | TokenType::Fun | TokenType::Var |
+                TokenType::For | TokenType::If | TokenType::While |
+                TokenType::Print | TokenType::Return => return,
+                _ => {}
+            }
+        }
+    }
+
+    fn declaration(&mut self) -> Result<Box<dyn Stmt>, Box<ParseError>> {
+        let doc = self.doc();
+        if self.eat(&TokenType::Class) {
+            return self.class_decl(doc);
+        }
+        if self.eat(&TokenType::Fun) {
+            return Ok(Box::new(self.function("function", doc)?));
         }
-        return statements;
+        if self.eat(&TokenType::Var) {
+            return self.var_decl(doc);
+        }
+        return self.statement();
+    }
+
+    fn class_decl(&mut self, doc: Option<String>) -> Result<Box<dyn Stmt>, Box<ParseError>> {
+        let name = self.consume(&TokenType::Identifier, "Expected class name")?;
+        let mut superclass = None;
+        if self.eat(&TokenType::Less) {
+            let superclass_name = self.consume(&TokenType::Identifier, "Expected superclass name")?;
+            superclass = Some(VariableExpr::new(superclass_name));
+        }
+        let left_brace = self.consume(&TokenType::LeftBrace, "Expected '{' before class body")?;
+        let mut methods = Vec::new();
+        while !self.is_at_end() && !self.match_token(&TokenType::RightBrace) {
+            let method_doc = self.doc();
+            methods.push(self.function("method", method_doc)?);
+        }
+        self.close(&TokenType::RightBrace, &left_brace, "Expected '}' after class body")?;
+        return Ok(Box::new(ClassStmt::new(name, superclass, methods, doc)));
     }
 
-    fn statement(&mut self) -> Box<dyn Stmt> {
-        if self.eat(&TokenType::Print) {
-            return self.print_stmt();
+    fn function(&mut self, kind: &str, doc: Option<String>) -> Result<FunctionStmt, Box<ParseError>> {
+        let name = self.consume(&TokenType::Identifier, &format!("Expected {} name", kind))?;
+        let left_paren = self.consume(&TokenType::LeftParen, &format!("Expected '(' after {} name", kind))?;
+        let mut params = Vec::new();
+        if !self.match_token(&TokenType::RightParen) {
+            loop {
+                params.push(self.consume(&TokenType::Identifier, "Expected parameter name")?);
+                if !self.eat(&TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.close(&TokenType::RightParen, &left_paren, "Expected ')' after parameters")?;
+        let left_brace = self.consume(&TokenType::LeftBrace, &format!("Expected '{{' before {} body", kind))?;
+        let statements = self.block()?;
+        let right_brace = self.close(&TokenType::RightBrace, &left_brace, &format!("Expected '}}' after {} body", kind))?;
+        let body = BlockStmt::new(left_brace, statements, right_brace);
+        return Ok(FunctionStmt::new(name, params, Rc::new(body), doc));
+    }
+
+    fn var_decl(&mut self, doc: Option<String>) -> Result<Box<dyn Stmt>, Box<ParseError>> {
+        let name = self.consume(&TokenType::Identifier, "Expected variable name")?;
+        let mut initializer = None;
+        if self.eat(&TokenType::Equal) {
+            initializer = Some(self.expr()?);
+        }
+        self.consume(&TokenType::Semicolon, "Expected ';' after variable declaration")?;
+        return Ok(Box::new(VarStmt::new(name, initializer, doc)));
+    }
+
+    fn statement(&mut self) -> Result<Box<dyn Stmt>, Box<ParseError>> {
+        return self.nested(Parser::unnested_statement);
+    }
+
+    fn unnested_statement(&mut self) -> Result<Box<dyn Stmt>, Box<ParseError>> {
+        if self.match_token(&TokenType::Print) {
+            let keyword = self.pull();
+            return self.print_stmt(keyword);
+        }
+        if self.match_token(&TokenType::If) {
+            let keyword = self.pull();
+            return self.if_stmt(keyword);
+        }
+        if self.match_token(&TokenType::While) {
+            let keyword = self.pull();
+            return self.while_stmt(keyword);
+        }
+        if self.match_token(&TokenType::For) {
+            let keyword = self.pull();
+            return self.for_stmt(keyword);
+        }
+        if self.match_token(&TokenType::Return) {
+            let keyword = self.pull();
+            return self.return_stmt(keyword);
+        }
+        if self.match_token(&TokenType::LeftBrace) {
+            let left_brace = self.pull();
+            let statements = self.block()?;
+            let right_brace = self.close(&TokenType::RightBrace, &left_brace, "Expected '}' after block")?;
+            return Ok(Box::new(BlockStmt::new(left_brace, statements, right_brace)));
         }
         return self.expr_stmt();
     }
 
-    fn expr_stmt(&mut self) -> Box<dyn Stmt> {
-        let expr = self.expr();
-        self.consume(&TokenType::Semicolon);
-        return Box::new(ExpressionStmt::new(expr));
+    fn if_stmt(&mut self, keyword: Token) -> Result<Box<dyn Stmt>, Box<ParseError>> {
+        let left_paren = self.consume(&TokenType::LeftParen, "Expected '(' after 'if'")?;
+        let condition = self.expr()?;
+        self.close(&TokenType::RightParen, &left_paren, "Expected ')' after if condition")?;
+        let then_branch = self.statement()?;
+        let mut else_branch = None;
+        if self.eat(&TokenType::Else) {
+            else_branch = Some(self.statement()?);
+        }
+        return Ok(Box::new(IfStmt::new(keyword, condition, then_branch, else_branch)));
+    }
+
+    fn while_stmt(&mut self, keyword: Token) -> Result<Box<dyn Stmt>, Box<ParseError>> {
+        let left_paren = self.consume(&TokenType::LeftParen, "Expected '(' after 'while'")?;
+        let condition = self.expr()?;
+        self.close(&TokenType::RightParen, &left_paren, "Expected ')' after while condition")?;
+        let body = self.statement()?;
+        return Ok(Box::new(WhileStmt::new(keyword, condition, body)));
+    }
+
+    fn for_stmt(&mut self, keyword: Token) -> Result<Box<dyn Stmt>, Box<ParseError>> {
+        let left_paren = self.consume(&TokenType::LeftParen, "Expected '(' after 'for'")?;
+        let initializer = if self.eat(&TokenType::Semicolon) {
+            None
+        } else if self.eat(&TokenType::Var) {
+            Some(self.var_decl(None)?)
+        } else {
+            Some(self.expr_stmt()?)
+        };
+        let mut condition = None;
+        if !self.match_token(&TokenType::Semicolon) {
+            condition = Some(self.expr()?);
+        }
+        self.consume(&TokenType::Semicolon, "Expected ';' after loop condition")?;
+        let mut increment = None;
+        if !self.match_token(&TokenType::RightParen) {
+            increment = Some(self.expr()?);
+        }
+        self.close(&TokenType::RightParen, &left_paren, "Expected ')' after for clauses")?;
+        let body = self.statement()?;
+        return Ok(Box::new(ForStmt::new(keyword, initializer, condition, increment, body)));
     }
 
-    fn print_stmt(&mut self) -> Box<dyn Stmt> {
-        let expr = self.expr();
-        self.consume(&TokenType::Semicolon);
-        return Box::new(PrintStmt::new(expr));
+    fn return_stmt(&mut self, keyword: Token) -> Result<Box<dyn Stmt>, Box<ParseError>> {
+        let mut value = None;
+        if !self.match_token(&TokenType::Semicolon) {
+            value = Some(self.expr()?);
+        }
+        self.consume(&TokenType::Semicolon, "Expected ';' after return value")?;
+        return Ok(Box::new(ReturnStmt::new(keyword, value)));
     }
 
-    fn expr(&mut self) -> Box<dyn Expr> {
-        return self.equality();
+    // Declarations up to, but not including, the closing '}'.
+    fn block(&mut self) -> Result<Vec<Box<dyn Stmt>>, Box<ParseError>> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() && !self.match_token(&TokenType::RightBrace) {
+            statements.push(self.nested(Parser::declaration)?);
+        }
+        return Ok(statements);
     }
 
-    fn equality(&mut self) -> Box<dyn Expr> {
-        let mut left = self.comparison();
+    fn expr_stmt(&mut self) -> Result<Box<dyn Stmt>, Box<ParseError>> {
+        let expr = self.expr()?;
+        self.consume(&TokenType::Semicolon, "Expected ';' after expression")?;
+        return Ok(Box::new(ExpressionStmt::new(expr)));
+    }
+
+    fn print_stmt(&mut self, keyword: Token) -> Result<Box<dyn Stmt>, Box<ParseError>> {
+        let expr = self.expr()?;
+        self.consume(&TokenType::Semicolon, "Expected ';' after value")?;
+        return Ok(Box::new(PrintStmt::new(keyword, expr)));
+    }
+
+    fn expr(&mut self) -> Result<Box<dyn Expr>, Box<ParseError>> {
+        return self.nested(Parser::assignment);
+    }
+
+    fn assignment(&mut self) -> Result<Box<dyn Expr>, Box<ParseError>> {
+        let expr = self.logic_or()?;
+        if self.match_token(&TokenType::Equal) {
+            let equals = self.pull();
+            let value = self.nested(Parser::assignment)?;
+            let expr = match expr.into_any().downcast::<VariableExpr>() {
+                Ok(variable) => return Ok(Box::new(AssignExpr::new(variable.get_name().clone(), value))),
+                Err(expr) => expr,
+            };
+            if let Ok(get) = expr.downcast::<GetExpr>() {
+                return Ok(Box::new(SetExpr::new(*get, value)));
+            }
+            return Err(Box::new(ParseError::new(Code::InvalidAssignmentTarget, equals, "Invalid assignment target".to_owned())));
+        }
+        return Ok(expr);
+    }
+
+    fn logic_or(&mut self) -> Result<Box<dyn Expr>, Box<ParseError>> {
+        let mut left = self.logic_and()?;
+        while self.match_token(&TokenType::Or) {
+            let op = self.pull();
+            let right = self.logic_and()?;
+            left = Box::new(LogicalExpr::new(left, op, right));
+        }
+        return Ok(left);
+    }
+
+    fn logic_and(&mut self) -> Result<Box<dyn Expr>, Box<ParseError>> {
+        let mut left = self.equality()?;
+        while self.match_token(&TokenType::And) {
+            let op = self.pull();
+            let right = self.equality()?;
+            left = Box::new(LogicalExpr::new(left, op, right));
+        }
+        return Ok(left);
+    }
+
+    fn equality(&mut self) -> Result<Box<dyn Expr>, Box<ParseError>> {
+        let mut left = self.comparison()?;
         while self.match_token(&TokenType::BangEqual) ||
             self.match_token(&TokenType::EqualEqual) {
             let op = self.pull();
-            let right = self.comparison();
+            let right = self.comparison()?;
             left = Box::new(BinaryExpr::new(left, op, right));
         }
-        return left;
+        return Ok(left);
     }
 
-    fn comparison(&mut self) -> Box<dyn Expr> {
-        let mut left = self.term();
+    fn comparison(&mut self) -> Result<Box<dyn Expr>, Box<ParseError>> {
+        let mut left = self.term()?;
         while self.match_token(&TokenType::Less) ||
             self.match_token(&TokenType::LessEqual) ||
             self.match_token(&TokenType::Greater) ||
             self.match_token(&TokenType::GreaterEqual) {
             let op = self.pull();
-            let right = self.term();
+            let right = self.term()?;
             left = Box::new(BinaryExpr::new(left, op, right));
         }
-        return left;
+        return Ok(left);
     }
 
-    fn term(&mut self) -> Box<dyn Expr> {
-        let mut left = self.factor();
+    fn term(&mut self) -> Result<Box<dyn Expr>, Box<ParseError>> {
+        let mut left = self.factor()?;
         while self.match_token(&TokenType::Plus) || self.match_token(&TokenType::Minus) {
             let op = self.pull();
-            let right = self.factor();
+            let right = self.factor()?;
             left = Box::new(BinaryExpr::new(left, op, right));
         }
-        return left;
+        return Ok(left);
     }
 
-    fn factor(&mut self) -> Box<dyn Expr> {
-        let mut left = self.unary();
+    fn factor(&mut self) -> Result<Box<dyn Expr>, Box<ParseError>> {
+        let mut left = self.unary()?;
         while self.match_token(&TokenType::Star) || self.match_token(&TokenType::Slash) {
             let op = self.pull();
-            let right = self.unary();
+            let right = self.unary()?;
             left = Box::new(BinaryExpr::new(left, op, right));
         }
-        return left;
+        return Ok(left);
     }
 
-    fn unary(&mut self) -> Box<dyn Expr> {
-        if self.match_token(&TokenType::Plus) || self.match_token(&TokenType::Minus) {
+    fn unary(&mut self) -> Result<Box<dyn Expr>, Box<ParseError>> {
+        if self.match_token(&TokenType::Plus) ||
+            self.match_token(&TokenType::Minus) ||
+            self.match_token(&TokenType::Bang) {
             let op = self.pull();
-            let right = self.primary();
-            return Box::new(UnaryExpr::new(op, right));
+            let right = self.nested(Parser::unary)?;
+            return Ok(Box::new(UnaryExpr::new(op, right)));
         }
-        return self.primary();
+        return self.call();
     }
 
-    fn primary(&mut self) -> Box<dyn Expr> {        
-        if self.match_token(&TokenType::NumberLiteral) {
-            return Box::new(LiteralExpr::new(self.pull()));
+    fn call(&mut self) -> Result<Box<dyn Expr>, Box<ParseError>> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.match_token(&TokenType::LeftParen) {
+                let left_paren = self.pull();
+                let mut arguments = Vec::new();
+                if !self.match_token(&TokenType::RightParen) {
+                    loop {
+                        arguments.push(self.expr()?);
+                        if !self.eat(&TokenType::Comma) {
+                            break;
+                        }
+                    }
+                }
+                let paren = self.close(&TokenType::RightParen, &left_paren, "Expected ')' after arguments")?;
+                expr = Box::new(CallExpr::new(expr, paren, arguments));
+            } else if self.eat(&TokenType::Dot) {
+                let name = self.consume(&TokenType::Identifier, "Expected property name after '.'")?;
+                expr = Box::new(GetExpr::new(expr, name));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Box<dyn Expr>, Box<ParseError>> {        
+        if self.match_token(&TokenType::NumberLiteral) ||
+            self.match_token(&TokenType::StringLiteral) ||
+            self.match_token(&TokenType::True) ||
+            self.match_token(&TokenType::False) ||
+            self.match_token(&TokenType::Nil) {
+            return Ok(Box::new(LiteralExpr::new(self.pull())));
+        }
+        if self.match_token(&TokenType::This) {
+            return Ok(Box::new(ThisExpr::new(self.pull())));
+        }
+        if self.match_token(&TokenType::Super) {
+            let keyword = self.pull();
+            self.consume(&TokenType::Dot, "Expected '.' after 'super'")?;
+            let method = self.consume(&TokenType::Identifier, "Expected superclass method name")?;
+            return Ok(Box::new(SuperExpr::new(keyword, method)));
+        }
+        if self.match_token(&TokenType::Identifier) {
+            return Ok(Box::new(VariableExpr::new(self.pull())));
         }
-        if self.eat(&TokenType::LeftParen) {
-            let exp = self.expr();
-            self.eat(&TokenType::RightParen);
-            return exp;
+        if self.match_token(&TokenType::LeftParen) {
+            let left_paren = self.pull();
+            let exp = self.expr()?;
+            self.close(&TokenType::RightParen, &left_paren, "Expected ')' after expression")?;
+            return Ok(exp);
         }
-        // TODO: throw error here
-        return Box::new(LiteralExpr::new(Token::new(TokenType::Unknown, "".to_string())));
+        return Err(self.error(Code::ExpectedExpression, "Expected expression"));
+    }
+
+    // Runs `parse` one level deeper, failing if that goes past the limit.
+    // Operator and call chains like `a + b + c` and `f()()` are parsed in a
+    // loop rather than through here, so only real nesting counts.
+    fn nested<T>(&mut self, parse: fn(&mut Parser) -> Result<T, Box<ParseError>>) -> Result<T, Box<ParseError>> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            return Err(self.error(Code::NestingTooDeep, "Too deeply nested"));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        return result;
+    }
+
+    // Documentation written before the current token.
+    fn doc(&self) -> Option<String> {
+        return self.docs.get(&self.current).cloned();
     }
 
     fn peek(&self) -> &Token {
         &self.tokens[self.current]
     }
 
+    // Returns the current token and moves past it. The trailing Eof is never
+    // moved past, so there is always a token to peek at.
     fn pull(&mut self) -> Token {
-        self.tokens.remove(self.current)
+        let token = self.tokens[self.current].clone();
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+        return token;
     }
 
     fn is_at_end(&self) -> bool {
@@ -144,11 +528,24 @@ impl Parser {
         return false;
     }
 
-    fn consume(&mut self, token_type: &TokenType) {
-        if self.eat(token_type) {
-            return;
+    fn consume(&mut self, token_type: &TokenType, message: &str) -> Result<Token, Box<ParseError>> {
+        if self.match_token(token_type) {
+            return Ok(self.pull());
         }
-        panic!("Couldn't consume {:?}. Current type: {:?}", token_type, self.peek().token_type);
+        return Err(self.error(Code::ExpectedToken, message));
+    }
+
+    // Like `consume`, for the closing half of a delimiter pair. The error
+    // points back at `opening` as well.
+    fn close(&mut self, token_type: &TokenType, opening: &Token, message: &str) -> Result<Token, Box<ParseError>> {
+        return self.consume(token_type, message)
+            .map_err(|error| Box::new(error.with_label(opening.span, format!("to match this '{}'", opening.literal))));
+    }
+
+    // Errors are boxed to keep results small, since every level of a deeply
+    // nested expression holds several of them on the stack.
+    fn error(&self, code: Code, message: &str) -> Box<ParseError> {
+        return Box::new(ParseError::new(code, self.peek().clone(), message.to_owned()));
     }
     
     fn match_token(&self, token_type: &TokenType) -> bool {
@@ -0,0 +1,218 @@
+use crate::expr::*;
+use crate::token::{Token, TokenType};
+use crate::value::Value;
+
+// Renders syntax trees for debugging and for tests of the parser. Every node
+// becomes a parenthesized S-expression headed by its operator or keyword:
+//
+//   1 + 2 * 3;           (expr (+ 1 (* 2 3)))
+//   var a = b.c(1);      (var a (call (. b c) 1))
+//
+// In tree layout the same nodes are drawn one per line, with children
+// indented under their head:
+//
+//   expr
+//     +
+//       1
+//       *
+//         2
+//         3
+//
+// Missing optional parts, like the clauses of `for (;;)`, are shown as `_`.
+pub struct AstPrinter {
+    tree: bool,
+}
+
+impl AstPrinter {
+    pub fn sexpr() -> AstPrinter {
+        AstPrinter {
+            tree: false,
+        }
+    }
+
+    pub fn tree() -> AstPrinter {
+        AstPrinter {
+            tree: true,
+        }
+    }
+
+    pub fn print_expr(&mut self, expr: &Box<dyn Expr>) -> String {
+        return expr.accept(self);
+    }
+
+    pub fn print_stmt(&mut self, stmt: &Box<dyn Stmt>) -> String {
+        return stmt.accept(self);
+    }
+
+    fn print_optional_expr(&mut self, expr: &Option<Box<dyn Expr>>) -> String {
+        return expr.as_ref().map_or("_".to_owned(), |expr| self.print_expr(expr));
+    }
+
+    fn parenthesize(&self, head: &str, children: Vec<String>) -> String {
+        if self.tree {
+            let mut out = head.to_owned();
+            for child in children {
+                for line in child.lines() {
+                    out.push_str("\n  ");
+                    out.push_str(line);
+                }
+            }
+            return out;
+        }
+
+        let mut out = format!("({}", head);
+        for child in children {
+            out.push(' ');
+            out.push_str(&child);
+        }
+        out.push(')');
+        return out;
+    }
+
+    fn params(params: &[Token]) -> String {
+        let names: Vec<&str> = params.iter().map(|param| param.literal.as_str()).collect();
+        return format!("({})", names.join(" "));
+    }
+}
+
+impl ExprVisitor<String> for AstPrinter {
+    fn visit_literal(&mut self, expr: &LiteralExpr) -> String {
+        let token = expr.get_token();
+        match token.token_type {
+            // Numbers are shown by value, so `0xFF` prints as 255.
+            TokenType::NumberLiteral => match token.number {
+                Some(value) => return Value::Number(value).to_string(),
+                None => return token.literal.clone(),
+            },
+            TokenType::StringLiteral => match &token.string {
+                Some(value) => return format!("{:?}", value),
+                None => return token.literal.clone(),
+            },
+            _ => return token.literal.clone(),
+        }
+    }
+
+    fn visit_unary(&mut self, expr: &UnaryExpr) -> String {
+        let right = self.print_expr(expr.get_right());
+        return self.parenthesize(&expr.get_op().literal, vec![right]);
+    }
+
+    fn visit_binary(&mut self, expr: &BinaryExpr) -> String {
+        let left = self.print_expr(expr.get_left());
+        let right = self.print_expr(expr.get_right());
+        return self.parenthesize(&expr.get_op().literal, vec![left, right]);
+    }
+
+    fn visit_variable(&mut self, expr: &VariableExpr) -> String {
+        return expr.get_name().literal.clone();
+    }
+
+    fn visit_assign(&mut self, expr: &AssignExpr) -> String {
+        let value = self.print_expr(expr.get_value());
+        return self.parenthesize("=", vec![expr.get_name().literal.clone(), value]);
+    }
+
+    fn visit_logical(&mut self, expr: &LogicalExpr) -> String {
+        let left = self.print_expr(expr.get_left());
+        let right = self.print_expr(expr.get_right());
+        return self.parenthesize(&expr.get_op().literal, vec![left, right]);
+    }
+
+    fn visit_call(&mut self, expr: &CallExpr) -> String {
+        let mut children = vec![self.print_expr(expr.get_callee())];
+        for argument in expr.get_arguments() {
+            children.push(self.print_expr(argument));
+        }
+        return self.parenthesize("call", children);
+    }
+
+    fn visit_get(&mut self, expr: &GetExpr) -> String {
+        let object = self.print_expr(expr.get_object());
+        return self.parenthesize(".", vec![object, expr.get_name().literal.clone()]);
+    }
+
+    fn visit_set(&mut self, expr: &SetExpr) -> String {
+        let target = self.visit_get(expr.get_target());
+        let value = self.print_expr(expr.get_value());
+        return self.parenthesize("=", vec![target, value]);
+    }
+
+    fn visit_this(&mut self, _expr: &ThisExpr) -> String {
+        return "this".to_owned();
+    }
+
+    fn visit_super(&mut self, expr: &SuperExpr) -> String {
+        return self.parenthesize("super", vec![expr.get_method().literal.clone()]);
+    }
+}
+
+impl StmtVisitor<String> for AstPrinter {
+    fn visit_expression(&mut self, stmt: &ExpressionStmt) -> String {
+        let expression = self.print_expr(stmt.get_expression());
+        return self.parenthesize("expr", vec![expression]);
+    }
+
+    fn visit_print(&mut self, stmt: &PrintStmt) -> String {
+        let expression = self.print_expr(stmt.get_expression());
+        return self.parenthesize("print", vec![expression]);
+    }
+
+    fn visit_var(&mut self, stmt: &VarStmt) -> String {
+        let mut children = vec![stmt.get_name().literal.clone()];
+        if let Some(initializer) = stmt.get_initializer() {
+            children.push(self.print_expr(initializer));
+        }
+        return self.parenthesize("var", children);
+    }
+
+    fn visit_block(&mut self, stmt: &BlockStmt) -> String {
+        let children = stmt.get_statements().iter().map(|statement| self.print_stmt(statement)).collect();
+        return self.parenthesize("block", children);
+    }
+
+    fn visit_if(&mut self, stmt: &IfStmt) -> String {
+        let mut children = vec![self.print_expr(stmt.get_condition()), self.print_stmt(stmt.get_then_branch())];
+        if let Some(else_branch) = stmt.get_else_branch() {
+            children.push(self.print_stmt(else_branch));
+        }
+        return self.parenthesize("if", children);
+    }
+
+    fn visit_while(&mut self, stmt: &WhileStmt) -> String {
+        let condition = self.print_expr(stmt.get_condition());
+        let body = self.print_stmt(stmt.get_body());
+        return self.parenthesize("while", vec![condition, body]);
+    }
+
+    fn visit_for(&mut self, stmt: &ForStmt) -> String {
+        let initializer = stmt.get_initializer().as_ref().map_or("_".to_owned(), |initializer| self.print_stmt(initializer));
+        let condition = self.print_optional_expr(stmt.get_condition());
+        let increment = self.print_optional_expr(stmt.get_increment());
+        let body = self.print_stmt(stmt.get_body());
+        return self.parenthesize("for", vec![initializer, condition, increment, body]);
+    }
+
+    fn visit_function(&mut self, stmt: &FunctionStmt) -> String {
+        let mut children = vec![stmt.get_name().literal.clone(), AstPrinter::params(stmt.get_params())];
+        for statement in stmt.get_body().get_statements() {
+            children.push(self.print_stmt(statement));
+        }
+        return self.parenthesize("fun", children);
+    }
+
+    fn visit_return(&mut self, stmt: &ReturnStmt) -> String {
+        let children = stmt.get_value().iter().map(|value| self.print_expr(value)).collect();
+        return self.parenthesize("return", children);
+    }
+
+    fn visit_class(&mut self, stmt: &ClassStmt) -> String {
+        let mut children = vec![stmt.get_name().literal.clone()];
+        if let Some(superclass) = stmt.get_superclass() {
+            children.push(self.parenthesize("<", vec![superclass.get_name().literal.clone()]));
+        }
+        for method in stmt.get_methods() {
+            children.push(self.visit_function(method));
+        }
+        return self.parenthesize("class", children);
+    }
+}
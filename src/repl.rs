@@ -0,0 +1,254 @@
+use std::env;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
+use proto_rust::lexer::{Lexer, KEYWORDS};
+use proto_rust::token::TokenType;
+use proto_rust::parser::Parser;
+use proto_rust::interpreter::Interpreter;
+use proto_rust::resolver::Resolver;
+use proto_rust::diagnostic::SourceMap;
+
+use crate::{emit, eval, print_ast, print_tokens, read, ErrorFormat, Input};
+
+const HELP: &str = "\
+:help           Show this message
+:quit           Leave the REPL (Ctrl-D works too)
+:reset          Forget every definition made so far
+:load <file>    Run a file in the current session
+:tokens <code>  List the tokens the lexer produces for <code>
+:ast <code>     Show the syntax tree the parser builds for <code>
+:tree <code>    Like :ast, but drawn as an indented tree
+:env            List defined globals and their values
+:time <code>    Run <code> and report how long it took";
+
+struct Session {
+    interpreter: Interpreter,
+    // Each entry is added as its own file so that spans, which the
+    // interpreter keys resolved variables by, never repeat. Old entries are
+    // kept for reporting errors in functions they defined.
+    sources: SourceMap,
+}
+
+// Tab-completes keywords and the globals defined when the line was started.
+struct ProtoHelper {
+    globals: Vec<String>,
+}
+
+impl Completer for ProtoHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].char_indices()
+            .rev()
+            .take_while(|(_, ch)| ch.is_alphanumeric())
+            .last()
+            .map_or(pos, |(i, _)| i);
+        let prefix = &line[start..pos];
+        if prefix.is_empty() {
+            return Ok((pos, Vec::new()));
+        }
+
+        let keywords = KEYWORDS.iter().map(|(keyword, _)| keyword.to_string());
+        let mut candidates: Vec<String> = keywords.chain(self.globals.iter().cloned())
+            .filter(|candidate| candidate.starts_with(prefix))
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        return Ok((start, candidates));
+    }
+}
+
+impl Hinter for ProtoHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ProtoHelper {}
+
+impl Validator for ProtoHelper {}
+
+impl Helper for ProtoHelper {}
+
+// History is kept in ~/.proto_history, or not at all if there is no home
+// directory.
+fn history_path() -> Option<PathBuf> {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    return Some(PathBuf::from(home).join(".proto_history"));
+}
+
+// Runs one REPL entry. A bare expression has its value printed. Unless
+// `finish` is set, returns false without running anything if the entry stops
+// partway through a string, comment or statement and more lines should be
+// read.
+fn eval_entry(interpreter: &mut Interpreter, sources: &SourceMap, file: usize, finish: bool) -> bool {
+    let code = &sources.get(file).source;
+    let mut lexer = Lexer::new(code.clone(), file);
+    let tokens = lexer.tokens();
+
+    if lexer.errors().is_empty() {
+        if let Ok(expr) = Parser::new(tokens.clone()).parse_expression() {
+            let mut resolver = Resolver::new(interpreter);
+            if let Err(errors) = resolver.resolve_expression(&expr) {
+                for error in errors {
+                    emit(sources, ErrorFormat::Human, &error.diagnostic());
+                }
+                return true;
+            }
+            match interpreter.interpret_expression(&expr) {
+                Ok(value) => println!("{}", value),
+                Err(error) => emit(sources, ErrorFormat::Human, &error.diagnostic()),
+            }
+            return true;
+        }
+    }
+
+    if !finish {
+        // Statements cut short fail on the Eof token.
+        let lex_incomplete = lexer.unterminated();
+        let parse_incomplete = match Parser::new(tokens).parse() {
+            Ok(_) => false,
+            Err(errors) => errors.iter().any(|error| error.found.token_type == TokenType::Eof),
+        };
+        if lex_incomplete || parse_incomplete {
+            return false;
+        }
+    }
+
+    let _ = eval(interpreter, sources, ErrorFormat::Human, file);
+    return true;
+}
+
+// Handles a `:` command. Returns false when the REPL should exit.
+fn command(session: &mut Session, line: &str) -> bool {
+    let line = line.trim();
+    let (name, argument) = match line.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (line, ""),
+    };
+
+    match name {
+        ":help" => println!("{}", HELP),
+        ":quit" => return false,
+        ":reset" => {
+            session.interpreter = Interpreter::new();
+            println!("Session reset");
+        }
+        ":load" => {
+            if argument.is_empty() {
+                eprintln!("Usage: :load <file>");
+                return true;
+            }
+            match read(&Input::File(argument.to_owned())) {
+                Ok(code) => {
+                    let file = session.sources.add(argument, code);
+                    let _ = eval(&mut session.interpreter, &session.sources, ErrorFormat::Human, file);
+                }
+                Err(message) => eprintln!("{}", message),
+            }
+        }
+        ":tokens" => {
+            let file = session.sources.add("<repl>", argument.to_owned());
+            let _ = print_tokens(&session.sources, ErrorFormat::Human, file);
+        }
+        ":ast" | ":tree" => {
+            let file = session.sources.add("<repl>", argument.to_owned());
+            let _ = print_ast(&session.sources, ErrorFormat::Human, file, name == ":tree");
+        }
+        ":env" => {
+            for name in session.interpreter.global_names() {
+                if let Some(value) = session.interpreter.global(&name) {
+                    println!("{} = {}", name, value);
+                }
+            }
+        }
+        ":time" => {
+            let file = session.sources.add("<repl>", argument.to_owned());
+            let start = Instant::now();
+            eval_entry(&mut session.interpreter, &session.sources, file, true);
+            println!("Took {:?}", start.elapsed());
+        }
+        _ => eprintln!("Unknown command '{}', see :help", name),
+    }
+    return true;
+}
+
+pub fn repl() {
+    let mut editor: Editor<ProtoHelper, DefaultHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(error) => {
+            eprintln!("Couldn't start the line editor: {}", error);
+            return;
+        }
+    };
+    editor.set_helper(Some(ProtoHelper { globals: Vec::new() }));
+    let history = history_path();
+    if let Some(path) = &history {
+        // A missing history file just means this is the first session.
+        let _ = editor.load_history(path);
+    }
+
+    let mut session = Session {
+        interpreter: Interpreter::new(),
+        sources: SourceMap::new(),
+    };
+    let mut code = String::new();
+
+    loop {
+        if let Some(helper) = editor.helper_mut() {
+            helper.globals = session.interpreter.global_names();
+        }
+        let prompt = if code.is_empty() { ">> " } else { ".. " };
+        let mut line = match editor.readline(prompt) {
+            Ok(line) => line,
+            // Ctrl-C abandons the current entry.
+            Err(ReadlineError::Interrupted) => {
+                code.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(error) => {
+                eprintln!("{}", error);
+                break;
+            }
+        };
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+        }
+        line.push('\n');
+
+        if code.is_empty() && line.trim_start().starts_with(':') {
+            if !command(&mut session, &line) {
+                break;
+            }
+            continue;
+        }
+
+        // An empty line ends a multi-line entry even if it is incomplete, so
+        // that its errors get reported.
+        let finish = !code.is_empty() && line.trim().is_empty();
+        code.push_str(&line);
+        if code.trim().is_empty() {
+            code.clear();
+            continue;
+        }
+
+        let file = session.sources.add("<repl>", code.clone());
+        if eval_entry(&mut session.interpreter, &session.sources, file, finish) {
+            code.clear();
+        }
+    }
+
+    if let Some(path) = &history {
+        if let Err(error) = editor.save_history(path) {
+            eprintln!("Couldn't save history to {}: {}", path.display(), error);
+        }
+    }
+}
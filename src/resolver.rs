@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+
+use crate::expr::*;
+use crate::interpreter::Interpreter;
+use crate::token::{Token, Span};
+use crate::diagnostic::{Code, Diagnostic, Label};
+
+#[derive(Debug,Clone)]
+pub struct ResolveError {
+    pub code: Code,
+    pub token: Token,
+    pub message: String,
+    pub labels: Vec<Label>,
+}
+
+impl ResolveError {
+    pub fn new(code: Code, token: Token, message: String) -> ResolveError {
+        ResolveError {
+            code,
+            token,
+            message,
+            labels: Vec::new(),
+        }
+    }
+
+    pub fn with_label(mut self, span: Span, message: String) -> ResolveError {
+        self.labels.push(Label::new(span, message));
+        return self;
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        return Diagnostic::error(self.code, self.token.span, self.message.clone()).with_labels(&self.labels);
+    }
+}
+
+impl std::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[{}] Error at '{}': {}", self.token.span, self.token.literal, self.message)
+    }
+}
+
+// What the resolver knows about a local name.
+#[derive(Clone,Copy)]
+struct Binding {
+    // False while the name's initializer is being resolved.
+    defined: bool,
+    // Where the name was declared. Empty for `this` and `super`.
+    span: Span,
+}
+
+#[derive(Clone,Copy,PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Clone,Copy,PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+// Walks a program before it runs, telling the interpreter how many scopes
+// out each local variable lives and reporting misuse of names that can be
+// detected statically.
+pub struct Resolver<'a> {
+    interpreter: &'a mut Interpreter,
+    // Innermost scope last. Globals are not tracked.
+    scopes: Vec<HashMap<String, Binding>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    errors: Vec<ResolveError>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(interpreter: &'a mut Interpreter) -> Resolver<'a> {
+        Resolver {
+            interpreter,
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn resolve(&mut self, statements: &Vec<Box<dyn Stmt>>) -> Result<(), Vec<ResolveError>> {
+        self.resolve_statements(statements);
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        return Err(std::mem::take(&mut self.errors));
+    }
+
+    pub fn resolve_expression(&mut self, expr: &Box<dyn Expr>) -> Result<(), Vec<ResolveError>> {
+        self.resolve_expr(expr);
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        return Err(std::mem::take(&mut self.errors));
+    }
+
+    fn resolve_statements(&mut self, statements: &Vec<Box<dyn Stmt>>) {
+        for statement in statements {
+            self.resolve_stmt(statement);
+        }
+    }
+
+    fn resolve_stmt(&mut self, stmt: &Box<dyn Stmt>) {
+        stmt.accept(self);
+    }
+
+    fn resolve_expr(&mut self, expr: &Box<dyn Expr>) {
+        expr.accept(self);
+    }
+
+    fn resolve_function(&mut self, function: &FunctionStmt, function_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+        self.begin_scope();
+        for param in function.get_params() {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_statements(function.get_body().get_statements());
+        self.end_scope();
+        self.current_function = enclosing_function;
+    }
+
+    fn resolve_local(&mut self, name: &Token) {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if scope.contains_key(&name.literal) {
+                self.interpreter.resolve(name, depth);
+                return;
+            }
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if let Some(previous) = scope.get(&name.literal) {
+                self.errors.push(ResolveError::new(Code::Redeclaration, name.clone(),
+                        format!("Already a variable named '{}' in this scope", name.literal))
+                    .with_label(previous.span, "first declared here".to_owned()));
+            }
+            scope.insert(name.literal.clone(), Binding { defined: false, span: name.span });
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.literal.clone(), Binding { defined: true, span: name.span });
+        }
+    }
+
+    fn define_name(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), Binding { defined: true, span: Span::default() });
+        }
+    }
+
+    fn error(&mut self, code: Code, token: &Token, message: &str) {
+        self.errors.push(ResolveError::new(code, token.clone(), message.to_owned()));
+    }
+}
+
+impl<'a> StmtVisitor<()> for Resolver<'a> {
+
+    fn visit_expression(&mut self, stmt: &ExpressionStmt) {
+        self.resolve_expr(stmt.get_expression());
+    }
+
+    fn visit_print(&mut self, stmt: &PrintStmt) {
+        self.resolve_expr(stmt.get_expression());
+    }
+
+    fn visit_var(&mut self, stmt: &VarStmt) {
+        self.declare(stmt.get_name());
+        if let Some(initializer) = stmt.get_initializer() {
+            self.resolve_expr(initializer);
+        }
+        self.define(stmt.get_name());
+    }
+
+    fn visit_block(&mut self, stmt: &BlockStmt) {
+        self.begin_scope();
+        self.resolve_statements(stmt.get_statements());
+        self.end_scope();
+    }
+
+    fn visit_if(&mut self, stmt: &IfStmt) {
+        self.resolve_expr(stmt.get_condition());
+        self.resolve_stmt(stmt.get_then_branch());
+        if let Some(else_branch) = stmt.get_else_branch() {
+            self.resolve_stmt(else_branch);
+        }
+    }
+
+    fn visit_while(&mut self, stmt: &WhileStmt) {
+        self.resolve_expr(stmt.get_condition());
+        self.resolve_stmt(stmt.get_body());
+    }
+
+    fn visit_for(&mut self, stmt: &ForStmt) {
+        self.begin_scope();
+        if let Some(initializer) = stmt.get_initializer() {
+            self.resolve_stmt(initializer);
+        }
+        if let Some(condition) = stmt.get_condition() {
+            self.resolve_expr(condition);
+        }
+        if let Some(increment) = stmt.get_increment() {
+            self.resolve_expr(increment);
+        }
+        self.resolve_stmt(stmt.get_body());
+        self.end_scope();
+    }
+
+    fn visit_function(&mut self, stmt: &FunctionStmt) {
+        self.declare(stmt.get_name());
+        self.define(stmt.get_name());
+        self.resolve_function(stmt, FunctionType::Function);
+    }
+
+    fn visit_return(&mut self, stmt: &ReturnStmt) {
+        if self.current_function == FunctionType::None {
+            self.error(Code::ReturnOutsideFunction, stmt.get_keyword(), "Can't return from top-level code");
+        }
+        if let Some(value) = stmt.get_value() {
+            if self.current_function == FunctionType::Initializer {
+                self.error(Code::ReturnFromInitializer, stmt.get_keyword(), "Can't return a value from an initializer");
+            }
+            self.resolve_expr(value);
+        }
+    }
+
+    fn visit_class(&mut self, stmt: &ClassStmt) {
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Class;
+        self.declare(stmt.get_name());
+        self.define(stmt.get_name());
+
+        if let Some(superclass) = stmt.get_superclass() {
+            if superclass.get_name().literal == stmt.get_name().literal {
+                self.error(Code::InheritFromSelf, superclass.get_name(), "A class can't inherit from itself");
+            }
+            self.current_class = ClassType::Subclass;
+            self.visit_variable(superclass);
+            self.begin_scope();
+            self.define_name("super");
+        }
+
+        self.begin_scope();
+        self.define_name("this");
+        for method in stmt.get_methods() {
+            let function_type = if method.get_name().literal == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.resolve_function(method, function_type);
+        }
+        self.end_scope();
+
+        if stmt.get_superclass().is_some() {
+            self.end_scope();
+        }
+        self.current_class = enclosing_class;
+    }
+}
+
+impl<'a> ExprVisitor<()> for Resolver<'a> {
+
+    fn visit_literal(&mut self, _expr: &LiteralExpr) {}
+
+    fn visit_unary(&mut self, expr: &UnaryExpr) {
+        self.resolve_expr(expr.get_right());
+    }
+
+    fn visit_binary(&mut self, expr: &BinaryExpr) {
+        self.resolve_expr(expr.get_left());
+        self.resolve_expr(expr.get_right());
+    }
+
+    fn visit_variable(&mut self, expr: &VariableExpr) {
+        let name = expr.get_name();
+        let binding = self.scopes.last().and_then(|scope| scope.get(&name.literal)).copied();
+        if let Some(binding) = binding.filter(|binding| !binding.defined) {
+            self.errors.push(ResolveError::new(Code::ReadInOwnInitializer, name.clone(), "Can't read local variable in its own initializer".to_owned())
+                .with_label(binding.span, "declared here".to_owned()));
+        }
+        self.resolve_local(name);
+    }
+
+    fn visit_assign(&mut self, expr: &AssignExpr) {
+        self.resolve_expr(expr.get_value());
+        self.resolve_local(expr.get_name());
+    }
+
+    fn visit_logical(&mut self, expr: &LogicalExpr) {
+        self.resolve_expr(expr.get_left());
+        self.resolve_expr(expr.get_right());
+    }
+
+    fn visit_call(&mut self, expr: &CallExpr) {
+        self.resolve_expr(expr.get_callee());
+        for argument in expr.get_arguments() {
+            self.resolve_expr(argument);
+        }
+    }
+
+    fn visit_get(&mut self, expr: &GetExpr) {
+        self.resolve_expr(expr.get_object());
+    }
+
+    fn visit_set(&mut self, expr: &SetExpr) {
+        self.resolve_expr(expr.get_value());
+        self.visit_get(expr.get_target());
+    }
+
+    fn visit_this(&mut self, expr: &ThisExpr) {
+        if self.current_class == ClassType::None {
+            self.error(Code::ThisOutsideClass, expr.get_keyword(), "Can't use 'this' outside of a class");
+            return;
+        }
+        self.resolve_local(expr.get_keyword());
+    }
+
+    fn visit_super(&mut self, expr: &SuperExpr) {
+        match self.current_class {
+            ClassType::None => self.error(Code::SuperOutsideClass, expr.get_keyword(), "Can't use 'super' outside of a class"),
+            ClassType::Class => self.error(Code::SuperWithoutSuperclass, expr.get_keyword(), "Can't use 'super' in a class with no superclass"),
+            ClassType::Subclass => self.resolve_local(expr.get_keyword()),
+        }
+    }
+}
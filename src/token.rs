@@ -13,21 +13,90 @@ pub enum TokenType {
     // Keywords.
     And, Class, Else, False, Fun, For, If, Nil, Or,
     Print, Return, Super, This, True, Var, While,
+    // A `///` comment line, kept for the parser to attach to the
+    // declaration that follows.
+    DocComment,
     // End of file.
-    Eof, Unknown
+    Eof
 }
 
+// Location of a piece of source text. `start` and `end` are byte offsets
+// into the file, `line` and `column` (both 1-based) point at `start`.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Default)]
+pub struct Span {
+    pub file: usize,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(file: usize, start: usize, end: usize, line: usize, column: usize) -> Span {
+        Span {
+            file,
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    // Span starting where `self` starts and ending where `other` ends.
+    pub fn to(&self, other: &Span) -> Span {
+        Span {
+            end: other.end,
+            ..*self
+        }
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+// `literal` is the token's text as written in the source. `number` and
+// `string` hold the value of a NumberLiteral or StringLiteral, worked out by
+// the lexer.
 #[derive(Debug,Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub literal: String,
+    pub number: Option<f64>,
+    pub string: Option<String>,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, literal: String) -> Token {
+    pub fn new(token_type: TokenType, literal: String, span: Span) -> Token {
         Token {
             token_type,
             literal,
+            number: None,
+            string: None,
+            span,
+        }
+    }
+
+    pub fn number(literal: String, value: f64, span: Span) -> Token {
+        Token {
+            token_type: TokenType::NumberLiteral,
+            literal,
+            number: Some(value),
+            string: None,
+            span,
+        }
+    }
+
+    pub fn string(literal: String, value: String, span: Span) -> Token {
+        Token {
+            token_type: TokenType::StringLiteral,
+            literal,
+            number: None,
+            string: Some(value),
+            span,
         }
     }
 }
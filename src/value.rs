@@ -0,0 +1,93 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::class::{Class, Instance};
+use crate::function::{Callable, Function, NativeFunction};
+
+// A runtime value. New kinds of values (callables, class instances, ...) are
+// added as further variants.
+#[derive(Clone)]
+pub enum Value {
+    Number(f64),
+    Bool(bool),
+    Str(String),
+    Nil,
+    Function(Rc<Function>),
+    Native(Rc<NativeFunction>),
+    Class(Rc<Class>),
+    Instance(Rc<RefCell<Instance>>),
+}
+
+impl Value {
+    // `nil` and `false` are falsey, everything else is truthy.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Nil => false,
+            Value::Bool(value) => *value,
+            _ => true,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::Bool(_) => "bool",
+            Value::Str(_) => "string",
+            Value::Nil => "nil",
+            Value::Function(_) | Value::Native(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+        }
+    }
+
+    pub fn as_callable(&self) -> Option<&dyn Callable> {
+        match self {
+            Value::Function(function) => Some(function.as_ref()),
+            Value::Native(function) => Some(function.as_ref()),
+            Value::Class(class) => Some(class),
+            _ => None,
+        }
+    }
+}
+
+// Values of different types are never equal.
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Nil, Value::Nil) => true,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Native(a), Value::Native(b)) => Rc::ptr_eq(a, b),
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(value) => write!(f, "{}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Str(value) => write!(f, "{}", value),
+            Value::Nil => write!(f, "nil"),
+            Value::Function(function) => write!(f, "<fn {}>", function.get_name().literal),
+            Value::Native(function) => write!(f, "<native fn {}>", function.get_name()),
+            Value::Class(class) => write!(f, "{}", class.get_name().literal),
+            Value::Instance(instance) => write!(f, "{} instance", instance.borrow().get_class().get_name().literal),
+        }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Str(value) => write!(f, "{:?}", value),
+            _ => write!(f, "{}", self),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// Golden tests for the shape of the trees the parser builds, printed with
+// AstPrinter.
+
+use proto_rust::lexer::Lexer;
+use proto_rust::parser::Parser;
+use proto_rust::printer::AstPrinter;
+
+fn sexpr(source: &str) -> String {
+    let tokens = Lexer::new(source.to_owned(), 0).tokens();
+    let statements = match Parser::new(tokens).parse() {
+        Ok(statements) => statements,
+        Err(errors) => panic!("parse errors in {:?}: {:?}", source, errors),
+    };
+    let mut printer = AstPrinter::sexpr();
+    let lines: Vec<String> = statements.iter().map(|statement| printer.print_stmt(statement)).collect();
+    return lines.join("\n");
+}
+
+fn expr(source: &str) -> String {
+    let tokens = Lexer::new(source.to_owned(), 0).tokens();
+    let expr = Parser::new(tokens).parse_expression().expect("not an expression");
+    return AstPrinter::sexpr().print_expr(&expr);
+}
+
+#[test]
+fn operators_nest_by_precedence() {
+    assert_eq!(expr("1 + 2 * 3"), "(+ 1 (* 2 3))");
+    assert_eq!(expr("(1 + 2) * 3"), "(* (+ 1 2) 3)");
+    assert_eq!(expr("1 - 2 - 3"), "(- (- 1 2) 3)");
+    assert_eq!(expr("-a < b == !c"), "(== (< (- a) b) (! c))");
+    assert_eq!(expr("a or b and c"), "(or a (and b c))");
+    assert_eq!(expr("a = b = 0x10"), "(= a (= b 16))");
+}
+
+#[test]
+fn calls_and_properties() {
+    assert_eq!(expr("f(1, \"two\")(x).y"), "(. (call (call f 1 \"two\") x) y)");
+    assert_eq!(expr("a.b.c = nil"), "(= (. (. a b) c) nil)");
+}
+
+#[test]
+fn statements() {
+    let source = "
+        class A < B { init(x) { this.x = x; return super.init(); } }
+        fun f() { for (;;) if (a) print 1; else { var b; } }
+        while (true) f();
+    ";
+    assert_eq!(sexpr(source), "\
+(class A (< B) (fun init (x) (expr (= (. this x) x)) (return (call (super init)))))
+(fun f () (for _ _ _ (if a (print 1) (block (var b)))))
+(while true (expr (call f)))");
+}
+
+#[test]
+fn tree_layout_indents_children() {
+    let tokens = Lexer::new("var x = 1 + -2;".to_owned(), 0).tokens();
+    let statements = Parser::new(tokens).parse().unwrap();
+    assert_eq!(AstPrinter::tree().print_stmt(&statements[0]), "\
+var
+  x
+  +
+    1
+    -
+      2");
+}
@@ -0,0 +1,100 @@
+// Classes, instances and inheritance, run through the binary.
+
+use std::process::Command;
+
+// Exit code, stdout and stderr of running `source`.
+fn run(source: &str) -> (i32, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_proto"))
+        .args(["-e", source])
+        .output()
+        .expect("failed to run proto");
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    return (output.status.code().expect("killed by a signal"), stdout, stderr);
+}
+
+fn output(source: &str) -> String {
+    let (code, stdout, stderr) = run(source);
+    assert_eq!(code, 0, "{}", stderr);
+    return stdout;
+}
+
+// The first line of the error `source` fails with at runtime.
+fn runtime_error(source: &str) -> String {
+    let (code, _, stderr) = run(source);
+    assert_eq!(code, 70, "{}", stderr);
+    return stderr.lines().next().unwrap_or("").to_owned();
+}
+
+#[test]
+fn init_returns_the_instance() {
+    let source = "
+        class Point {
+            init(x) {
+                this.x = x;
+                if (x == \"early\") return;
+                this.late = true;
+            }
+        }
+        var p = Point(\"a\");
+        print p;
+        print p.init(\"b\") == p;
+        print p.x;
+        var q = Point(\"early\");
+        print q.x;
+        print q.init(\"early\") == q;
+    ";
+    assert_eq!(output(source), "Point instance\ntrue\nb\nearly\ntrue\n");
+}
+
+#[test]
+fn bound_methods_keep_this() {
+    let source = "
+        class Greeter {
+            init(name) { this.name = name; }
+            greet() { return \"hi \" + this.name; }
+        }
+        var greet = Greeter(\"a\").greet;
+        var other = Greeter(\"b\");
+        other.greet = greet;
+        print greet();
+        print other.greet();
+    ";
+    assert_eq!(output(source), "hi a\nhi a\n");
+}
+
+#[test]
+fn super_calls_the_superclass_method() {
+    let source = "
+        class A {
+            name() { return \"A\"; }
+            describe() { return \"I am \" + this.name(); }
+        }
+        class B < A {
+            name() { return \"B\"; }
+            describe() { return super.describe() + \", from \" + super.name(); }
+        }
+        class C < B {}
+        print C().describe();
+    ";
+    assert_eq!(output(source), "I am B, from A\n");
+}
+
+#[test]
+fn fields_shadow_methods() {
+    let source = "
+        class A { m() { return \"method\"; } }
+        var a = A();
+        a.m = \"field\";
+        print a.m;
+        print A().m();
+    ";
+    assert_eq!(output(source), "field\nmethod\n");
+}
+
+#[test]
+fn class_errors() {
+    assert_eq!(runtime_error("var C = 1; class D < C {}"), "error[E0307]: Superclass must be a class");
+    assert_eq!(runtime_error("class C {} C().x;"), "error[E0302]: Undefined property 'x'");
+    assert_eq!(runtime_error("class C { m() {} } C().m2();"), "error[E0302]: Undefined property 'm2'");
+}
@@ -0,0 +1,88 @@
+// Command line handling of the `proto` binary: commands, inputs, options and
+// exit codes.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// Exit code, stdout and stderr of running proto with `arguments`, feeding it
+// `stdin`.
+fn proto(arguments: &[&str], stdin: &str) -> (i32, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_proto"))
+        .args(arguments)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run proto");
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    return (output.status.code().expect("killed by a signal"), stdout, stderr);
+}
+
+fn exit_code(arguments: &[&str]) -> i32 {
+    return proto(arguments, "").0;
+}
+
+#[test]
+fn exit_codes() {
+    assert_eq!(exit_code(&["-e", "print 1;"]), 0);
+    assert_eq!(exit_code(&["--error-format=xml", "-e", "print 1;"]), 64);
+    assert_eq!(exit_code(&["run", "-e"]), 64);
+    assert_eq!(exit_code(&["-e", "print ;"]), 65);
+    assert_eq!(exit_code(&["-e", "{ var a; var a; }"]), 65);
+    assert_eq!(exit_code(&["no such file.proto"]), 66);
+    assert_eq!(exit_code(&["-e", "print nil + 1;"]), 70);
+}
+
+#[test]
+fn inline_and_stdin_input() {
+    assert_eq!(proto(&["-e", "print 1 + 2;"], ""), (0, "3\n".to_owned(), String::new()));
+    assert_eq!(proto(&["run", "-e", "print arg(0);", "x"], ""), (0, "x\n".to_owned(), String::new()));
+    assert_eq!(proto(&["-"], "print \"piped\";"), (0, "piped\n".to_owned(), String::new()));
+    assert_eq!(proto(&["check", "-"], "print \"piped\";"), (0, String::new(), String::new()));
+}
+
+#[test]
+fn error_format_before_and_after_the_command() {
+    let json = "{\"severity\":\"error\",\"code\":\"E0102\"";
+    for arguments in [
+        &["--error-format=json", "check", "-e", "print ;"][..],
+        &["--error-format", "json", "check", "-e", "print ;"],
+        &["check", "--error-format=json", "-e", "print ;"],
+        &["check", "--error-format", "json", "-e", "print ;"],
+    ] {
+        let (code, _, stderr) = proto(arguments, "");
+        assert_eq!(code, 65, "{:?}", arguments);
+        assert!(stderr.starts_with(json), "{:?}: {}", arguments, stderr);
+    }
+    let (_, _, stderr) = proto(&["check", "-e", "print ;"], "");
+    assert!(stderr.starts_with("error[E0102]"), "{}", stderr);
+}
+
+#[test]
+fn ast_tree() {
+    assert_eq!(proto(&["ast", "-e", "1 + 2;"], ""), (0, "(expr (+ 1 2))\n".to_owned(), String::new()));
+    assert_eq!(proto(&["ast", "--tree", "-e", "1 + 2;"], ""), (0, "expr\n  +\n    1\n    2\n".to_owned(), String::new()));
+}
+
+// Only `run` passes the arguments after the input on to the script.
+#[test]
+fn extra_arguments_are_rejected() {
+    for command in ["check", "tokens", "ast"] {
+        let (code, stdout, stderr) = proto(&[command, "-e", "1;", "extra"], "");
+        assert_eq!((code, stdout.as_str()), (64, ""), "{}", command);
+        assert!(stderr.starts_with("proto: Unexpected argument 'extra'"), "{}: {}", command, stderr);
+    }
+}
+
+// `:load` in the REPL describes a file it can't read the same way as
+// running it would.
+#[test]
+fn repl_load_reports_read_errors() {
+    let (code, _, stderr) = proto(&["no such file.proto"], "");
+    assert_eq!((code, stderr.as_str()), (66, "proto: Couldn't read 'no such file.proto': no such file\n"));
+    let (code, _, stderr) = proto(&["repl"], ":load no such file.proto\n");
+    assert_eq!((code, stderr.as_str()), (0, "Couldn't read 'no such file.proto': no such file\n"));
+}
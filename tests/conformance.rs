@@ -0,0 +1,176 @@
+// Precedence and associativity conformance cases. Each program leaves its
+// answer in the global `result`, which is compared against the value the
+// grammar in parser.rs says it should have.
+
+use std::thread;
+
+use proto_rust::STACK_SIZE;
+use proto_rust::interpreter::Interpreter;
+use proto_rust::lexer::Lexer;
+use proto_rust::parser::Parser;
+use proto_rust::resolver::Resolver;
+use proto_rust::value::Value;
+
+fn run(source: &str) -> Value {
+    let mut lexer = Lexer::new(source.to_owned(), 0);
+    let tokens = lexer.tokens();
+    assert!(lexer.errors().is_empty(), "lex errors in {:?}", source);
+    let statements = match Parser::new(tokens).parse() {
+        Ok(statements) => statements,
+        Err(errors) => panic!("parse errors in {:?}: {:?}", source, errors),
+    };
+    let mut interpreter = Interpreter::new();
+    if let Err(errors) = Resolver::new(&mut interpreter).resolve(&statements) {
+        panic!("resolve errors in {:?}: {:?}", source, errors);
+    }
+    if let Err(error) = interpreter.interpret(&statements) {
+        panic!("runtime error in {:?}: {}", source, error);
+    }
+    return interpreter.global("result").expect("program did not set result");
+}
+
+fn eval(expr: &str) -> Value {
+    return run(&format!("var result = {};", expr));
+}
+
+fn parses(source: &str) -> bool {
+    let tokens = Lexer::new(source.to_owned(), 0).tokens();
+    return Parser::new(tokens).parse().is_ok();
+}
+
+#[test]
+fn unary_binds_tighter_than_factor() {
+    assert_eq!(eval("-2 * 3"), Value::Number(-6.0));
+    assert_eq!(eval("-2 - 3"), Value::Number(-5.0));
+    assert_eq!(eval("!true == false"), Value::Bool(true));
+}
+
+#[test]
+fn unary_operators_chain() {
+    assert_eq!(eval("- -1"), Value::Number(1.0));
+    assert_eq!(eval("--1"), Value::Number(1.0));
+    assert_eq!(eval("-+-2"), Value::Number(2.0));
+    assert_eq!(eval("!!true"), Value::Bool(true));
+    assert_eq!(eval("!!nil"), Value::Bool(false));
+    assert_eq!(eval("!-1"), Value::Bool(false));
+    assert_eq!(run("var x = 3; var result = --x;"), Value::Number(3.0));
+}
+
+#[test]
+fn unary_applies_to_calls() {
+    assert_eq!(run("fun two() { return 2; } var result = -two();"), Value::Number(-2.0));
+    assert_eq!(run("fun yes() { return true; } var result = !yes();"), Value::Bool(false));
+}
+
+#[test]
+fn factor_binds_tighter_than_term() {
+    assert_eq!(eval("1 + 2 * 3"), Value::Number(7.0));
+    assert_eq!(eval("1 * 2 + 3"), Value::Number(5.0));
+    assert_eq!(eval("10 - 6 / 2"), Value::Number(7.0));
+}
+
+#[test]
+fn binary_operators_are_left_associative() {
+    assert_eq!(eval("1 - 2 - 3"), Value::Number(-4.0));
+    assert_eq!(eval("16 / 4 / 2"), Value::Number(2.0));
+    assert_eq!(eval("2 * 3 / 6"), Value::Number(1.0));
+    assert_eq!(eval("\"a\" + \"b\" + \"c\""), Value::Str("abc".to_owned()));
+}
+
+#[test]
+fn grouping_overrides_precedence() {
+    assert_eq!(eval("2 * (3 + 4)"), Value::Number(14.0));
+    assert_eq!(eval("1 - (2 - 3)"), Value::Number(2.0));
+    assert_eq!(eval("-(2 + 3)"), Value::Number(-5.0));
+    assert_eq!(eval("((((1))))"), Value::Number(1.0));
+}
+
+#[test]
+fn comparison_binds_tighter_than_equality() {
+    assert_eq!(eval("1 < 2 == true"), Value::Bool(true));
+    assert_eq!(eval("1 + 1 == 2"), Value::Bool(true));
+    assert_eq!(eval("3 > 2 != 2 > 3"), Value::Bool(true));
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    assert_eq!(eval("true or false and false"), Value::Bool(true));
+    assert_eq!(eval("false and true or true"), Value::Bool(true));
+    assert_eq!(eval("nil or 1 == 1"), Value::Bool(true));
+}
+
+#[test]
+fn logical_operators_return_operands() {
+    assert_eq!(eval("nil or \"default\""), Value::Str("default".to_owned()));
+    assert_eq!(eval("1 and 2"), Value::Number(2.0));
+    assert_eq!(eval("false and 1"), Value::Bool(false));
+}
+
+#[test]
+fn assignment_is_right_associative() {
+    assert_eq!(run("var a; var b; var result = a = b = 3;"), Value::Number(3.0));
+    assert_eq!(run("var a; var b; a = b = 4; var result = a;"), Value::Number(4.0));
+    assert_eq!(run("var a = 1; var result = a = 1 + 2 * 3;"), Value::Number(7.0));
+}
+
+#[test]
+fn calls_and_property_access_chain() {
+    let source = "
+        class Box { init(value) { this.value = value; } get() { return this.value; } }
+        fun make(value) { return Box(value); }
+        var result = make(5).get() * 2;
+    ";
+    assert_eq!(run(source), Value::Number(10.0));
+    assert_eq!(run("fun f() { fun g() { return 7; } return g; } var result = f()();"), Value::Number(7.0));
+}
+
+#[test]
+fn number_literal_forms() {
+    assert_eq!(eval("0xFF + 0b1010 + 0o17"), Value::Number(280.0));
+    assert_eq!(eval("1_000 * 1e3"), Value::Number(1_000_000.0));
+    assert_eq!(eval("-2.5E-1"), Value::Number(-0.25));
+}
+
+#[test]
+fn invalid_targets_and_dangling_operators_are_rejected() {
+    assert!(!parses("1 + 2 = 3;"));
+    assert!(!parses("-x = 1;"));
+    assert!(!parses("1 +;"));
+    assert!(!parses("* 2;"));
+    assert!(!parses("(1 + 2;"));
+    assert!(parses("-x;"));
+}
+
+// Runs `test` on a thread with the stack a program gets from the binary.
+fn with_program_stack(test: fn()) {
+    thread::Builder::new().stack_size(STACK_SIZE).spawn(test).unwrap().join().unwrap();
+}
+
+#[test]
+fn deep_nesting_is_rejected_instead_of_overflowing() {
+    with_program_stack(|| {
+        let nested = |open: &str, inner: &str, close: &str, depth: usize| {
+            return format!("{}{}{}", open.repeat(depth), inner, close.repeat(depth));
+        };
+        assert!(!parses(&format!("print {};", nested("(", "1", ")", 3000))));
+        assert!(!parses(&format!("print {}1;", "-".repeat(3000))));
+        assert!(!parses(&nested("{", "", "}", 3000)));
+        assert!(!parses(&nested("fun f() {", "", "}", 3000)));
+        assert_eq!(eval(&nested("(", "1", ")", 1000)), Value::Number(1.0));
+        assert_eq!(eval(&format!("{}1", "-".repeat(1000))), Value::Number(1.0));
+    });
+}
+
+// Chains of operators and calls are flat in the source, however long, so
+// they don't count as nesting.
+#[test]
+fn long_chains_are_not_nesting() {
+    with_program_stack(|| {
+        assert_eq!(eval(&format!("0{}", "+1".repeat(3000))), Value::Number(3000.0));
+        assert_eq!(eval(&format!("\"\"{}", " + \"a\"".repeat(130))), Value::Str("a".repeat(130)));
+        assert!(parses(&format!("f{};", "()".repeat(3000))));
+        let cases: Vec<String> = (0..200).map(|n| format!("if (n == {}) result = {};", n, n)).collect();
+        let source = format!("var n = 199; var result; {}", cases.join(" else "));
+        assert_eq!(run(&source), Value::Number(199.0));
+    });
+}
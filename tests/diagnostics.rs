@@ -0,0 +1,124 @@
+// Rendering of diagnostics produced by each stage, in plain (uncolored) mode
+// and as JSON.
+
+use proto_rust::diagnostic::{Emitter, JsonEmitter, SourceMap};
+use proto_rust::interpreter::Interpreter;
+use proto_rust::lexer::Lexer;
+use proto_rust::parser::Parser;
+use proto_rust::resolver::Resolver;
+
+fn source(code: &str) -> (SourceMap, usize) {
+    let mut sources = SourceMap::new();
+    let file = sources.add("test.proto", code.to_owned());
+    return (sources, file);
+}
+
+#[test]
+fn lex_error_with_help() {
+    let (sources, file) = source("print \"a\\q\";\n");
+    let mut lexer = Lexer::new(sources.get(file).source.clone(), file);
+    lexer.tokens();
+    let rendered = Emitter::new(&sources, false).render(&lexer.errors()[0].diagnostic());
+    assert_eq!(rendered, "\
+error[E0003]: Invalid escape sequence '\\q'
+ --> test.proto:1:9
+  |
+1 | print \"a\\q\";
+  |         ^^
+  |
+  = help: Valid escapes are '\\n', '\\t', '\\\\', '\\\"' and '\\u{...}'
+
+");
+}
+
+#[test]
+fn parse_error_points_at_opening_delimiter() {
+    let (sources, file) = source("print (1 +\n  2;\n");
+    let tokens = Lexer::new(sources.get(file).source.clone(), file).tokens();
+    let errors = Parser::new(tokens).parse().unwrap_err();
+    let rendered = Emitter::new(&sources, false).render(&errors[0].diagnostic());
+    assert_eq!(rendered, "\
+error[E0101]: Expected ')' after expression, found ';'
+ --> test.proto:2:4
+  |
+1 | print (1 +
+  |       - to match this '('
+2 |   2;
+  |    ^
+
+");
+}
+
+#[test]
+fn resolve_error_labels_previous_declaration() {
+    let (sources, file) = source("{\n  var a = 1;\n\n  var a = 2;\n}\n");
+    let tokens = Lexer::new(sources.get(file).source.clone(), file).tokens();
+    let statements = Parser::new(tokens).parse().unwrap();
+    let mut interpreter = Interpreter::new();
+    let errors = Resolver::new(&mut interpreter).resolve(&statements).unwrap_err();
+    let rendered = Emitter::new(&sources, false).render(&errors[0].diagnostic());
+    assert_eq!(rendered, "\
+error[E0201]: Already a variable named 'a' in this scope
+ --> test.proto:4:7
+  |
+2 |   var a = 1;
+  |       - first declared here
+...
+4 |   var a = 2;
+  |       ^
+
+");
+}
+
+#[test]
+fn runtime_error_labels_operand_types() {
+    let (sources, file) = source("var n = nil;\nprint n * 2;\n");
+    let tokens = Lexer::new(sources.get(file).source.clone(), file).tokens();
+    let statements = Parser::new(tokens).parse().unwrap();
+    let mut interpreter = Interpreter::new();
+    Resolver::new(&mut interpreter).resolve(&statements).unwrap();
+    let error = interpreter.interpret(&statements).unwrap_err();
+    let rendered = Emitter::new(&sources, false).render(&error.diagnostic());
+    assert_eq!(rendered, "\
+error[E0303]: Operands must be numbers, found nil and number
+ --> test.proto:2:9
+  |
+2 | print n * 2;
+  |         ^
+  |       - nil
+  |           - number
+
+");
+}
+
+#[test]
+fn json_reports_codes_and_end_positions() {
+    let (sources, file) = source("var x = 1;\nprint (x +\n  \"\\q\");\n");
+    let mut lexer = Lexer::new(sources.get(file).source.clone(), file);
+    lexer.tokens();
+    let rendered = JsonEmitter::new(&sources).render(&lexer.errors()[0].diagnostic());
+    assert_eq!(rendered, concat!(
+        r#"{"severity":"error","code":"E0003","message":"Invalid escape sequence '\\q'","file":"test.proto","#,
+        r#""start":{"line":3,"column":4},"end":{"line":3,"column":6},"labels":[],"notes":[],"#,
+        r#""help":"Valid escapes are '\\n', '\\t', '\\\\', '\\\"' and '\\u{...}'"}"#));
+}
+
+// Codes of the errors `proto check` reports for `source`, in order.
+fn check_codes(source: &str) -> Vec<String> {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_proto"))
+        .args(["check", "--error-format=json", "-e", source])
+        .output()
+        .expect("failed to run proto");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    return stderr.lines().map(|line| line[line.find("\"code\":").unwrap() + 8..][..5].to_owned()).collect();
+}
+
+#[test]
+fn unterminated_string_is_reported_once() {
+    assert_eq!(check_codes("var b = \"abc"), vec!["E0002"]);
+}
+
+#[test]
+fn lex_errors_dont_hide_resolve_errors() {
+    assert_eq!(check_codes("{ var a = 1; var a = 2; } print \"\\q\";"), vec!["E0003", "E0201"]);
+}
@@ -0,0 +1,84 @@
+// Calls, returns and closures, run through the binary so that programs get
+// the same stack they would from the command line.
+
+use std::process::Command;
+
+// Exit code, stdout and stderr of running `source`.
+fn run(source: &str) -> (i32, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_proto"))
+        .args(["-e", source])
+        .output()
+        .expect("failed to run proto");
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    return (output.status.code().expect("killed by a signal"), stdout, stderr);
+}
+
+// Recursion is limited by how deep statements and expressions nest overall,
+// so nested blocks in a recursive function use up the same budget as calls.
+#[test]
+fn deep_recursion_through_nested_blocks() {
+    let body = format!("{}if (n == 0) return 0; return 1 + f(n - 1);{}", "{".repeat(30), "}".repeat(30));
+    let (code, stdout, stderr) = run(&format!("fun f(n) {{ {} }} print f(250);", body));
+    assert_eq!((code, stdout.as_str()), (0, "250\n"), "{}", stderr);
+
+    let (code, stdout, stderr) = run(&format!("fun f(n) {{ {} }} print f(100000);", body));
+    assert_eq!((code, stdout.as_str()), (70, ""));
+    assert!(stderr.starts_with("error[E0306]: Stack overflow"), "{}", stderr);
+}
+
+// The first line of the error `source` fails with at runtime.
+fn runtime_error(source: &str) -> String {
+    let (code, _, stderr) = run(source);
+    assert_eq!(code, 70, "{}", stderr);
+    return stderr.lines().next().unwrap_or("").to_owned();
+}
+
+#[test]
+fn closures_capture_their_environment() {
+    let source = "
+        fun counter() {
+            var count = 0;
+            fun increment() { count = count + 1; return count; }
+            return increment;
+        }
+        var a = counter();
+        var b = counter();
+        a(); a();
+        print a();
+        print b();
+    ";
+    assert_eq!(run(source), (0, "3\n1\n".to_owned(), String::new()));
+}
+
+#[test]
+fn return_unwinds_out_of_loops() {
+    let source = "
+        fun find() {
+            var n = 0;
+            while (true) {
+                for (var i = 0; i < 10; i = i + 1) {
+                    n = n + 1;
+                    if (i == 3) return n;
+                }
+            }
+        }
+        fun nothing() { while (true) return; }
+        print find();
+        print nothing();
+    ";
+    assert_eq!(run(source), (0, "4\nnil\n".to_owned(), String::new()));
+}
+
+#[test]
+fn calls_check_the_callee() {
+    assert_eq!(runtime_error("fun f(a) {} f(1, 2);"), "error[E0305]: Expected 1 arguments but got 2");
+    assert_eq!(runtime_error("class A { init(x) {} } A();"), "error[E0305]: Expected 1 arguments but got 0");
+    assert_eq!(runtime_error("var a = 1; a();"), "error[E0304]: Can only call functions and classes, found number");
+    assert_eq!(runtime_error("\"f\"();"), "error[E0304]: Can only call functions and classes, found string");
+}
+
+#[test]
+fn unbounded_recursion_is_a_runtime_error() {
+    assert_eq!(runtime_error("fun f() { f(); } f();"), "error[E0306]: Stack overflow");
+}
@@ -0,0 +1,201 @@
+// Tokens and errors produced by the lexer, including how it recovers from
+// bad input.
+
+use proto_rust::diagnostic::Code;
+use proto_rust::expr::{ClassStmt, FunctionStmt, VarStmt};
+use proto_rust::lexer::Lexer;
+use proto_rust::parser::Parser;
+use proto_rust::token::{Token, TokenType};
+
+fn lex(source: &str) -> (Vec<Token>, Vec<(Code, String)>) {
+    let mut lexer = Lexer::new(source.to_owned(), 0);
+    let tokens = lexer.tokens();
+    let errors = lexer.errors().iter().map(|error| (error.code, error.message.clone())).collect();
+    return (tokens, errors);
+}
+
+fn types(tokens: &[Token]) -> Vec<TokenType> {
+    return tokens.iter().map(|token| token.token_type.clone()).collect();
+}
+
+#[test]
+fn nul_byte_is_an_error_not_the_end() {
+    let (tokens, errors) = lex("print 1;\0 print 2;");
+    assert_eq!(types(&tokens), vec![
+        TokenType::Print, TokenType::NumberLiteral, TokenType::Semicolon,
+        TokenType::Print, TokenType::NumberLiteral, TokenType::Semicolon,
+        TokenType::Eof,
+    ]);
+    assert_eq!(errors, vec![(Code::UnexpectedCharacter, "Unexpected character '\\0'".to_owned())]);
+}
+
+#[test]
+fn nul_byte_inside_comments_and_strings() {
+    let (tokens, errors) = lex("// a\0b\n/* \0 */ \"x\0y\"");
+    assert_eq!(types(&tokens), vec![TokenType::StringLiteral, TokenType::Eof]);
+    assert_eq!(errors, vec![]);
+}
+
+#[test]
+fn errors_are_collected_and_lexing_continues() {
+    let (tokens, errors) = lex("var a = 1 @ 2;\nprint # a;");
+    assert_eq!(types(&tokens), vec![
+        TokenType::Var, TokenType::Identifier, TokenType::Equal, TokenType::NumberLiteral,
+        TokenType::NumberLiteral, TokenType::Semicolon,
+        TokenType::Print, TokenType::Identifier, TokenType::Semicolon,
+        TokenType::Eof,
+    ]);
+    assert_eq!(errors, vec![
+        (Code::UnexpectedCharacter, "Unexpected character '@'".to_owned()),
+        (Code::UnexpectedCharacter, "Unexpected character '#'".to_owned()),
+    ]);
+    assert_eq!(tokens[4].span.line, 1);
+    assert_eq!(tokens[7].span.line, 2);
+}
+
+#[test]
+fn unterminated_string_still_produces_a_token() {
+    let mut lexer = Lexer::new("print 1 @;\nvar b = \"abc\ndef".to_owned(), 0);
+    let tokens = lexer.tokens();
+    assert_eq!(types(&tokens), vec![
+        TokenType::Print, TokenType::NumberLiteral, TokenType::Semicolon,
+        TokenType::Var, TokenType::Identifier, TokenType::Equal, TokenType::StringLiteral,
+        TokenType::Eof,
+    ]);
+    let codes: Vec<Code> = lexer.errors().iter().map(|error| error.code).collect();
+    assert_eq!(codes, vec![Code::UnexpectedCharacter, Code::UnterminatedString]);
+    assert!(lexer.unterminated());
+    // The token spans the rest of the input, from the opening quote.
+    assert_eq!(tokens[6].span.end, 27);
+}
+
+#[test]
+fn string_escapes_are_decoded() {
+    let source = r#""a\nb\tc\\d\"e\u{1F600}\u{41}""#;
+    let (tokens, errors) = lex(source);
+    assert_eq!(errors, vec![]);
+    assert_eq!(tokens[0].string.as_deref(), Some("a\nb\tc\\d\"e\u{1F600}A"));
+    // The literal is still the text as written.
+    assert_eq!(tokens[0].literal, source);
+}
+
+#[test]
+fn invalid_escapes_are_reported_and_dropped() {
+    let (tokens, errors) = lex(r#""a\qb\u{110000}c\u{}d\u41e""#);
+    assert_eq!(tokens[0].string.as_deref(), Some("abcd41e"));
+    assert_eq!(errors, vec![
+        (Code::InvalidEscape, "Invalid escape sequence '\\q'".to_owned()),
+        (Code::InvalidEscape, "Invalid unicode escape '\\u{110000}'".to_owned()),
+        (Code::InvalidEscape, "Invalid unicode escape '\\u{}'".to_owned()),
+        (Code::InvalidEscape, "Invalid unicode escape '\\u'".to_owned()),
+    ]);
+}
+
+#[test]
+fn malformed_numbers_are_rejected() {
+    for source in ["1_e5", "1.5_e3", "1e_5", "1_.5", "1__0", "1_", "0x", "0x_1", "0xF_", "1e", "1e+", ".5", "0b12", "0o8", "12abc"] {
+        let (tokens, errors) = lex(source);
+        assert_eq!(types(&tokens), vec![TokenType::NumberLiteral, TokenType::Eof], "in {:?}", source);
+        assert_eq!(tokens[0].number, None, "in {:?}", source);
+        let codes: Vec<Code> = errors.into_iter().map(|(code, _)| code).collect();
+        assert_eq!(codes, vec![Code::MalformedNumber], "in {:?}", source);
+    }
+}
+
+#[test]
+fn separators_between_digits_are_allowed() {
+    for (source, value) in [("1_000", 1000.0), ("1_0.2_5e1_0", 10.25e10), ("0xF_F", 255.0), ("0b1_0", 2.0), ("0o1_7", 15.0)] {
+        let (tokens, errors) = lex(source);
+        assert_eq!(errors, vec![], "in {:?}", source);
+        assert_eq!(tokens[0].number, Some(value), "in {:?}", source);
+    }
+}
+
+#[test]
+fn block_comments_nest() {
+    let (tokens, errors) = lex("1 /* a /* b */ c */ 2 /**/ 3");
+    assert_eq!(errors, vec![]);
+    let numbers: Vec<Option<f64>> = tokens.iter().map(|token| token.number).collect();
+    assert_eq!(numbers, vec![Some(1.0), Some(2.0), Some(3.0), None]);
+}
+
+#[test]
+fn unterminated_block_comment() {
+    let mut lexer = Lexer::new("1 /* a /* b */ c".to_owned(), 0);
+    let tokens = lexer.tokens();
+    assert_eq!(types(&tokens), vec![TokenType::NumberLiteral, TokenType::Eof]);
+    assert_eq!(lexer.errors().len(), 1);
+    assert_eq!(lexer.errors()[0].code, Code::UnterminatedComment);
+    assert_eq!((lexer.errors()[0].span.start, lexer.errors()[0].span.end), (2, 16));
+    assert!(lexer.unterminated());
+}
+
+#[test]
+fn doc_comments_become_tokens() {
+    let (tokens, errors) = lex("// plain\n/// one\r\n////// rule\n\t///two\nvar a; /// trailing\n");
+    assert_eq!(errors, vec![]);
+    assert_eq!(types(&tokens), vec![
+        TokenType::DocComment, TokenType::DocComment,
+        TokenType::Var, TokenType::Identifier, TokenType::Semicolon,
+        TokenType::Eof,
+    ]);
+    assert_eq!(tokens[0].literal, "/// one\r");
+    assert_eq!(tokens[1].literal, "///two");
+}
+
+fn docs(source: &str) -> Vec<Option<String>> {
+    let tokens = Lexer::new(source.to_owned(), 0).tokens();
+    let statements = Parser::new(tokens).parse().expect("parse errors");
+    let mut docs = Vec::new();
+    for statement in statements {
+        let statement = statement.into_any();
+        if let Some(var) = statement.downcast_ref::<VarStmt>() {
+            docs.push(var.get_doc().clone());
+        } else if let Some(function) = statement.downcast_ref::<FunctionStmt>() {
+            docs.push(function.get_doc().clone());
+        } else if let Some(class) = statement.downcast_ref::<ClassStmt>() {
+            docs.push(class.get_doc().clone());
+            docs.extend(class.get_methods().iter().map(|method| method.get_doc().clone()));
+        } else {
+            docs.push(None);
+        }
+    }
+    return docs;
+}
+
+#[test]
+fn doc_comments_attach_to_declarations() {
+    let source = "
+        /// The answer.
+        var answer = 42;
+        /// Adds two numbers.
+        ///
+        /// Both must be numbers.
+        fun add(a, b) { return a + b; }
+        //// Not documentation.
+        var plain;
+        /// A point.
+        class Point {
+            /// Makes a point.
+            init(x, y) {}
+            norm() {}
+        }
+        /// Dropped, since a print statement has nowhere to keep it.
+        print 1;
+        var after;
+        var trailing = 1; /// About trailing, not the next declaration.
+        var next;
+    ";
+    assert_eq!(docs(source), vec![
+        Some("The answer.".to_owned()),
+        Some("Adds two numbers.\n\nBoth must be numbers.".to_owned()),
+        None,
+        Some("A point.".to_owned()),
+        Some("Makes a point.".to_owned()),
+        None,
+        None,
+        None,
+        None,
+        None,
+    ]);
+}
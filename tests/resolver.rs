@@ -0,0 +1,93 @@
+// Static checks made by the resolver, and the bindings it settles before the
+// program runs.
+
+use std::process::Command;
+
+use proto_rust::diagnostic::Code;
+use proto_rust::interpreter::Interpreter;
+use proto_rust::lexer::Lexer;
+use proto_rust::parser::Parser;
+use proto_rust::resolver::Resolver;
+
+// Codes and messages of every resolve error in `source`, in order.
+fn resolve_errors(source: &str) -> Vec<(Code, String)> {
+    let tokens = Lexer::new(source.to_owned(), 0).tokens();
+    let statements = match Parser::new(tokens).parse() {
+        Ok(statements) => statements,
+        Err(errors) => panic!("parse errors in {:?}: {:?}", source, errors),
+    };
+    let mut interpreter = Interpreter::new();
+    match Resolver::new(&mut interpreter).resolve(&statements) {
+        Ok(()) => return Vec::new(),
+        Err(errors) => return errors.into_iter().map(|error| (error.code, error.message)).collect(),
+    }
+}
+
+fn assert_error(source: &str, code: Code, message: &str) {
+    assert_eq!(resolve_errors(source), vec![(code, message.to_owned())], "in {:?}", source);
+}
+
+#[test]
+fn read_in_own_initializer() {
+    assert_error("{ var a = a; }", Code::ReadInOwnInitializer, "Can't read local variable in its own initializer");
+    // Globals are looked up at runtime, so this only fails when it runs.
+    assert_eq!(resolve_errors("var a = a;"), vec![]);
+}
+
+#[test]
+fn return_outside_function() {
+    assert_error("return 1;", Code::ReturnOutsideFunction, "Can't return from top-level code");
+    assert_error("{ return; }", Code::ReturnOutsideFunction, "Can't return from top-level code");
+}
+
+#[test]
+fn return_value_from_initializer() {
+    assert_error("class A { init() { return 1; } }", Code::ReturnFromInitializer, "Can't return a value from an initializer");
+    assert_eq!(resolve_errors("class A { init() { return; } }"), vec![]);
+}
+
+#[test]
+fn this_outside_class() {
+    assert_error("print this;", Code::ThisOutsideClass, "Can't use 'this' outside of a class");
+    assert_error("fun f() { return this; }", Code::ThisOutsideClass, "Can't use 'this' outside of a class");
+}
+
+#[test]
+fn super_without_superclass() {
+    assert_error("class A { m() { super.m(); } }", Code::SuperWithoutSuperclass, "Can't use 'super' in a class with no superclass");
+    assert_error("fun f() { super.m(); }", Code::SuperOutsideClass, "Can't use 'super' outside of a class");
+}
+
+#[test]
+fn redeclaration_in_same_scope() {
+    assert_error("{ var a = 1; var a = 2; }", Code::Redeclaration, "Already a variable named 'a' in this scope");
+    assert_error("fun f(a, a) {}", Code::Redeclaration, "Already a variable named 'a' in this scope");
+    // Shadowing in an inner scope and redefining globals are both fine.
+    assert_eq!(resolve_errors("{ var a = 1; { var a = 2; } }"), vec![]);
+    assert_eq!(resolve_errors("var a = 1; var a = 2;"), vec![]);
+}
+
+#[test]
+fn inherit_from_self() {
+    assert_error("class A < A {}", Code::InheritFromSelf, "A class can't inherit from itself");
+}
+
+#[test]
+fn all_errors_are_reported() {
+    let errors = resolve_errors("return; print this; { var a = a; }");
+    let codes: Vec<Code> = errors.into_iter().map(|(code, _)| code).collect();
+    assert_eq!(codes, vec![Code::ReturnOutsideFunction, Code::ThisOutsideClass, Code::ReadInOwnInitializer]);
+}
+
+// A closure keeps the binding it saw when it was resolved, even if a later
+// declaration in the same block shadows the name.
+#[test]
+fn closure_binding_is_fixed_before_shadowing() {
+    let source = "var a = \"global\"; { fun show() { print a; } show(); var a = \"block\"; show(); }";
+    let output = Command::new(env!("CARGO_BIN_EXE_proto"))
+        .args(["-e", source])
+        .output()
+        .expect("failed to run proto");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "global\nglobal\n");
+}
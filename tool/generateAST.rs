@@ -1,15 +1,16 @@
 use std::fs;
 
 fn define_visitor(code: &mut String, base_name: &str, fields: &[&str]) {
-    *code = format!("{}pub trait {}Visitor {{\n", code, base_name);
+    *code = format!("{}pub trait {}Visitor<R> {{\n", code, base_name);
     for field in fields {
         let type_name = field.split('!').next().unwrap();
-        *code = format!("{}    fn visit_{}(&mut self, {}: &{}{}) -> f64;\n", code, type_name.to_lowercase(), type_name.to_lowercase(), type_name, base_name);
+        *code = format!("{}    fn visit_{}(&mut self, {}: &{}{}) -> R;\n", code, type_name.to_lowercase(), base_name.to_lowercase(), type_name, base_name);
     }
     *code = format!("{}}}\n\n", code);
 }
 
-fn define_type(code: &mut String, base_name: &str, class_name: &str, field_name: &str) {
+fn define_type(code: &mut String, base_name: &str, class_name: &str, field_name: &str, span: &str) {
+    *code = format!("{}#[derive(Debug)]\n", code);
     *code = format!("{}pub struct {}{} {{\n", code, class_name, base_name);
     let some = field_name.split(',');
     for field in some {
@@ -39,45 +40,86 @@ fn define_type(code: &mut String, base_name: &str, class_name: &str, field_name:
 
     *code = format!("{}}}\n\n", code);
 
-    *code = format!("{}impl {} for {}{} {{\n", code, base_name, class_name, base_name);
-    *code = format!("{}    fn accept(&self, visitor: &mut dyn {}Visitor) -> f64 {{\n", code, base_name);
+    *code = format!("{}impl<R> {}Accept<R> for {}{} {{\n", code, base_name, class_name, base_name);
+    *code = format!("{}    fn accept(&self, visitor: &mut dyn {}Visitor<R>) -> R {{\n", code, base_name);
     *code = format!("{}        return visitor.visit_{}(self);\n", code, class_name.to_lowercase());
     *code = format!("{}    }}\n", code);
     *code = format!("{}}}\n\n", code);
+
+    *code = format!("{}impl {} for {}{} {{\n", code, base_name, class_name, base_name);
+    *code = format!("{}    fn span(&self) -> Span {{\n", code);
+    *code = format!("{}        return {};\n", code, span);
+    *code = format!("{}    }}\n", code);
+    *code = format!("{}    fn into_any(self: Box<Self>) -> Box<dyn Any> {{\n", code);
+    *code = format!("{}        return self;\n", code);
+    *code = format!("{}    }}\n", code);
+    *code = format!("{}}}\n\n", code);
 }
 
-fn define_ast(code: &mut String, base_name: &str, fields: &[&str]) {
+// Every visitor result type needs an `{base}Accept` supertrait on `{base}` so
+// that trees of trait objects can be walked by all visitors.
+fn define_ast(code: &mut String, base_name: &str, return_types: &[&str], fields: &[&str]) {
     define_visitor(code, &base_name, &fields);
 
-    *code = format!("{}pub trait {} {{\n", code, base_name);
-    *code = format!("{}    fn accept(&self, visitor: &mut dyn {}Visitor) -> f64;\n", code, base_name);
+    *code = format!("{}pub trait {}Accept<R> {{\n", code, base_name);
+    *code = format!("{}    fn accept(&self, visitor: &mut dyn {}Visitor<R>) -> R;\n", code, base_name);
+    *code = format!("{}}}\n\n", code);
+
+    let accepts: Vec<String> = return_types.iter()
+        .map(|return_type| format!("{}Accept<{}>", base_name, return_type))
+        .collect();
+    *code = format!("{}pub trait {}: {} + Debug {{\n", code, base_name, accepts.join(" + "));
+    *code = format!("{}    fn span(&self) -> Span;\n", code);
+    *code = format!("{}    fn into_any(self: Box<Self>) -> Box<dyn Any>;\n", code);
     *code = format!("{}}}\n\n", code);
 
     for field in fields {
         let mut some = field.split('!');
         let class_name = some.next().unwrap().trim();
         let field_name = some.next().unwrap().trim();
-        define_type(code, base_name, class_name, field_name);
+        let span = some.next().unwrap().trim();
+        define_type(code, base_name, class_name, field_name, span);
     }
 }
 
 fn main() {
     let mut code = String::new();
 
-    code.push_str("use crate::token::Token;\n\n");
+    code.push_str("use std::any::Any;\n");
+    code.push_str("use std::fmt::Debug;\n");
+    code.push_str("use std::rc::Rc;\n");
+    code.push_str("use crate::token::{Token, Span};\n");
+    code.push_str("use crate::interpreter::{RuntimeError, Unwind};\n");
+    code.push_str("use crate::value::Value;\n\n");
 
-    define_ast(&mut code, "Expr",
+    define_ast(&mut code, "Expr", &["Result<Value, RuntimeError>", "()", "String"],
         &[
-            "Literal! token: Token",
-            "Unary! op: Token, right: Box<dyn Expr>",
-            "Binary! left: Box<dyn Expr>, op: Token, right: Box<dyn Expr>",
+            "Literal! token: Token ! self.token.span",
+            "Unary! op: Token, right: Box<dyn Expr> ! self.op.span.to(&self.right.span())",
+            "Binary! left: Box<dyn Expr>, op: Token, right: Box<dyn Expr> ! self.left.span().to(&self.right.span())",
+            "Variable! name: Token ! self.name.span",
+            "Assign! name: Token, value: Box<dyn Expr> ! self.name.span.to(&self.value.span())",
+            "Logical! left: Box<dyn Expr>, op: Token, right: Box<dyn Expr> ! self.left.span().to(&self.right.span())",
+            "Call! callee: Box<dyn Expr>, paren: Token, arguments: Vec<Box<dyn Expr>> ! self.callee.span().to(&self.paren.span)",
+            "Get! object: Box<dyn Expr>, name: Token ! self.object.span().to(&self.name.span)",
+            "Set! target: GetExpr, value: Box<dyn Expr> ! self.target.span().to(&self.value.span())",
+            "This! keyword: Token ! self.keyword.span",
+            "Super! keyword: Token, method: Token ! self.keyword.span.to(&self.method.span)",
         ]
     );
 
-    define_ast(&mut code, "Stmt",
+    define_ast(&mut code, "Stmt", &["Result<(), Unwind>", "()", "String"],
         &[
-            "Expression! expression: Box<dyn Expr>",
-            "Print! expression: Box<dyn Expr>"
+            "Expression! expression: Box<dyn Expr> ! self.expression.span()",
+            "Print! keyword: Token, expression: Box<dyn Expr> ! self.keyword.span.to(&self.expression.span())",
+            "Var! name: Token, initializer: Option<Box<dyn Expr>>, doc: Option<String> ! self.initializer.as_ref().map_or(self.name.span, |initializer| self.name.span.to(&initializer.span()))",
+            "Block! left_brace: Token, statements: Vec<Box<dyn Stmt>>, right_brace: Token ! self.left_brace.span.to(&self.right_brace.span)",
+            "If! keyword: Token, condition: Box<dyn Expr>, then_branch: Box<dyn Stmt>, else_branch: Option<Box<dyn Stmt>> ! self.keyword.span.to(&self.else_branch.as_ref().unwrap_or(&self.then_branch).span())",
+            "While! keyword: Token, condition: Box<dyn Expr>, body: Box<dyn Stmt> ! self.keyword.span.to(&self.body.span())",
+            "For! keyword: Token, initializer: Option<Box<dyn Stmt>>, condition: Option<Box<dyn Expr>>, increment: Option<Box<dyn Expr>>, body: Box<dyn Stmt> ! self.keyword.span.to(&self.body.span())",
+            "Function! name: Token, params: Vec<Token>, body: Rc<BlockStmt>, doc: Option<String> ! self.name.span.to(&self.body.span())",
+            "Return! keyword: Token, value: Option<Box<dyn Expr>> ! self.value.as_ref().map_or(self.keyword.span, |value| self.keyword.span.to(&value.span()))",
+            "Class! name: Token, superclass: Option<VariableExpr>, methods: Vec<FunctionStmt>, doc: Option<String> ! self.methods.last().map_or(self.name.span, |method| self.name.span.to(&method.span()))"
         ]
     );
 